@@ -0,0 +1,446 @@
+use crate::{provider::Provider, secret::Secret, shell};
+use anyhow::{bail, Result};
+use clap::ValueEnum;
+use serde::Deserialize;
+use std::{fmt, path::Path};
+
+const WEBDAV_VENDORS: [&str; 8] = [
+    "other",
+    "nextcloud",
+    "owncloud",
+    "infinitescale",
+    "sharepoint",
+    "sharepoint-ntlm",
+    "rclone",
+    "fastmail",
+];
+
+/// The kind of rclone remote a job backs up to.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    /// An S3-compatible object store, see --provider
+    #[default]
+    S3,
+    /// A directory on an SSH server
+    Sftp,
+    /// A WebDAV share such as Nextcloud
+    Webdav,
+    /// A local directory or mounted NAS share
+    Local,
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Backend::S3 => write!(f, "s3"),
+            Backend::Sftp => write!(f, "sftp"),
+            Backend::Webdav => write!(f, "webdav"),
+            Backend::Local => write!(f, "local"),
+        }
+    }
+}
+
+impl Backend {
+    /// Remote name used when `--remote` is not given.
+    pub fn default_remote_name(&self) -> &'static str {
+        match self {
+            Backend::S3 => "minio",
+            Backend::Sftp => "sftp",
+            Backend::Webdav => "webdav",
+            Backend::Local => "local",
+        }
+    }
+}
+
+/// The resolved settings of a job's remote, one variant per backend.
+#[derive(Debug)]
+pub enum RemoteSetup {
+    S3 {
+        provider: Provider,
+        endpoint: Option<String>,
+        region: Option<String>,
+        account_id: Option<String>,
+        location_constraint: Option<String>,
+        access_key: Secret,
+        secret_key: Secret,
+    },
+    Sftp {
+        host: String,
+        port: Option<u16>,
+        user: Option<String>,
+        key_file: Option<String>,
+        password: Option<Secret>,
+    },
+    Webdav {
+        url: String,
+        vendor: String,
+        user: Option<String>,
+        password: Option<Secret>,
+    },
+    Local,
+}
+
+/// Every key `RemoteSetup::entries` writes for any backend. Setup drops
+/// these when a remote no longer needs them and leaves all others alone.
+pub const REMOTE_KEYS: &[&str] = &[
+    "type",
+    "provider",
+    "env_auth",
+    "access_key_id",
+    "secret_access_key",
+    "region",
+    "endpoint",
+    "location_constraint",
+    "host",
+    "user",
+    "port",
+    "key_file",
+    "pass",
+    "url",
+    "vendor",
+];
+
+impl RemoteSetup {
+    pub fn backend(&self) -> Backend {
+        match self {
+            RemoteSetup::S3 { .. } => Backend::S3,
+            RemoteSetup::Sftp { .. } => Backend::Sftp,
+            RemoteSetup::Webdav { .. } => Backend::Webdav,
+            RemoteSetup::Local => Backend::Local,
+        }
+    }
+
+    /// The remote's rclone.conf entries. Passwords are returned in plain
+    /// text; `setup` obscures them before they are written.
+    pub fn entries(&self) -> Vec<(&'static str, String)> {
+        let mut entries = vec![("type", self.backend().to_string())];
+        let mut optional = Vec::new();
+        match self {
+            RemoteSetup::S3 {
+                provider,
+                endpoint,
+                region,
+                location_constraint,
+                access_key,
+                secret_key,
+                ..
+            } => {
+                entries.extend([
+                    ("provider", provider.rclone_name().to_string()),
+                    ("env_auth", "false".to_string()),
+                    ("access_key_id", access_key.expose().to_string()),
+                    ("secret_access_key", secret_key.expose().to_string()),
+                ]);
+                optional.extend([
+                    ("region", region.clone()),
+                    ("endpoint", endpoint.clone()),
+                    ("location_constraint", location_constraint.clone()),
+                ]);
+            }
+            RemoteSetup::Sftp {
+                host,
+                port,
+                user,
+                key_file,
+                password,
+            } => {
+                entries.push(("host", host.clone()));
+                optional.extend([
+                    ("user", user.clone()),
+                    ("port", port.map(|p| p.to_string())),
+                    ("key_file", key_file.clone()),
+                    ("pass", password.as_ref().map(|p| p.expose().to_string())),
+                ]);
+            }
+            RemoteSetup::Webdav {
+                url,
+                vendor,
+                user,
+                password,
+            } => {
+                entries.extend([("url", url.clone()), ("vendor", vendor.clone())]);
+                optional.extend([
+                    ("user", user.clone()),
+                    ("pass", password.as_ref().map(|p| p.expose().to_string())),
+                ]);
+            }
+            RemoteSetup::Local => {}
+        }
+        entries.extend(
+            optional
+                .into_iter()
+                .filter_map(|(key, value)| value.map(|v| (key, v))),
+        );
+        entries
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        match self {
+            RemoteSetup::S3 {
+                provider,
+                endpoint,
+                region,
+                account_id,
+                location_constraint,
+                access_key,
+                secret_key,
+            } => {
+                for (what, value) in [
+                    ("Endpoint", endpoint.as_deref()),
+                    ("Region", region.as_deref()),
+                    ("Account ID", account_id.as_deref()),
+                    ("Location constraint", location_constraint.as_deref()),
+                ] {
+                    if let Some(value) = value {
+                        shell::reject_control_chars(what, value)?;
+                    }
+                }
+                shell::reject_secret_control_chars("Access key", access_key)?;
+                shell::reject_secret_control_chars("Secret key", secret_key)?;
+                provider.validate(
+                    region.as_deref(),
+                    account_id.as_deref(),
+                    location_constraint.as_deref(),
+                    endpoint.as_deref(),
+                )?;
+                if access_key.expose().trim().is_empty() {
+                    bail!("Access key must not be empty");
+                }
+                if secret_key.expose().trim().is_empty() {
+                    bail!("Secret key must not be empty");
+                }
+            }
+            RemoteSetup::Sftp {
+                host,
+                user,
+                key_file,
+                password,
+                ..
+            } => {
+                if host.is_empty() || host.contains(char::is_whitespace) || host.starts_with('-') {
+                    bail!(
+                        "SFTP host must be non-empty, without spaces and must not start with '-', got '{}'",
+                        host
+                    );
+                }
+                validate_user_and_password(user.as_deref(), password.as_ref())?;
+                if let Some(key_file) = key_file {
+                    shell::reject_control_chars("Key file", key_file)?;
+                    if !Path::new(key_file).is_file() {
+                        bail!("SFTP key file does not exist: {}", key_file);
+                    }
+                }
+            }
+            RemoteSetup::Webdav {
+                url,
+                vendor,
+                user,
+                password,
+            } => {
+                shell::reject_control_chars("WebDAV URL", url)?;
+                if !url.starts_with("http://") && !url.starts_with("https://") {
+                    bail!(
+                        "WebDAV URL must start with http:// or https://, got '{}'",
+                        url
+                    );
+                }
+                if !WEBDAV_VENDORS.contains(&vendor.as_str()) {
+                    bail!(
+                        "Unknown WebDAV vendor '{}', expected one of {}",
+                        vendor,
+                        WEBDAV_VENDORS.join(", ")
+                    );
+                }
+                validate_user_and_password(user.as_deref(), password.as_ref())?;
+            }
+            RemoteSetup::Local => {}
+        }
+        Ok(())
+    }
+}
+
+fn validate_user_and_password(user: Option<&str>, password: Option<&Secret>) -> Result<()> {
+    if let Some(user) = user {
+        shell::reject_control_chars("User", user)?;
+        if user.is_empty() {
+            bail!("User must not be empty");
+        }
+    }
+    if let Some(password) = password {
+        shell::reject_secret_control_chars("Password", password)?;
+        if password.expose().is_empty() {
+            bail!("Password must not be empty");
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::{self, TempDir};
+
+    fn remote(args: &[&str]) -> anyhow::Result<super::RemoteSetup> {
+        let base = ["--source", "/", "--bucket", "/backups"];
+        let args: Vec<&str> = base.iter().chain(args).copied().collect();
+        let job = testing::setup_jobs(&args, "")?.remove(0);
+        job.remote_setup.validate()?;
+        Ok(job.remote_setup)
+    }
+
+    fn entries(args: &[&str]) -> Vec<(&'static str, String)> {
+        remote(args).unwrap().entries()
+    }
+
+    fn owned(entries: &[(&'static str, &str)]) -> Vec<(&'static str, String)> {
+        entries.iter().map(|(k, v)| (*k, v.to_string())).collect()
+    }
+
+    #[test]
+    fn sftp_entries_hold_only_what_was_given() {
+        let dir = TempDir::new();
+        let key = dir.path().join("id_ed25519");
+        std::fs::write(&key, "").unwrap();
+        let key = key.to_string_lossy();
+        assert_eq!(
+            entries(&["--backend", "sftp", "--host", "nas.lan"]),
+            owned(&[("type", "sftp"), ("host", "nas.lan")])
+        );
+        assert_eq!(
+            entries(&[
+                "--backend",
+                "sftp",
+                "--host",
+                "nas.lan",
+                "--port",
+                "2222",
+                "--user",
+                "backup",
+                "--key-file",
+                &key,
+            ]),
+            owned(&[
+                ("type", "sftp"),
+                ("host", "nas.lan"),
+                ("user", "backup"),
+                ("port", "2222"),
+                ("key_file", &key),
+            ])
+        );
+    }
+
+    #[test]
+    fn webdav_and_local_entries() {
+        assert_eq!(
+            entries(&[
+                "--backend",
+                "webdav",
+                "--url",
+                "https://cloud.lan/dav",
+                "--webdav-vendor",
+                "nextcloud",
+                "--user",
+                "me",
+                "--password",
+                "pw",
+            ]),
+            owned(&[
+                ("type", "webdav"),
+                ("url", "https://cloud.lan/dav"),
+                ("vendor", "nextcloud"),
+                ("user", "me"),
+                ("pass", "pw"),
+            ])
+        );
+        assert_eq!(
+            entries(&["--backend", "local"]),
+            owned(&[("type", "local")])
+        );
+    }
+
+    #[test]
+    fn every_written_key_is_owned() {
+        let dir = TempDir::new();
+        let key = dir.path().join("key");
+        std::fs::write(&key, "").unwrap();
+        let key = key.to_string_lossy();
+        for args in [
+            &["--access-key", "a", "--secret-key", "b", "--region", "x"][..],
+            &[
+                "--backend",
+                "sftp",
+                "--host",
+                "h",
+                "--user",
+                "u",
+                "--password",
+                "p",
+                "--port",
+                "22",
+                "--key-file",
+                &key,
+            ],
+            &[
+                "--backend",
+                "webdav",
+                "--url",
+                "http://h",
+                "--user",
+                "u",
+                "--password",
+                "p",
+            ],
+        ] {
+            for (key, _) in entries(args) {
+                assert!(super::REMOTE_KEYS.contains(&key), "{}", key);
+            }
+        }
+    }
+
+    #[test]
+    fn backend_settings_are_validated() {
+        for (args, message) in [
+            (
+                &["--backend", "sftp", "--host=-oProxyCommand=x"][..],
+                "SFTP host must be",
+            ),
+            (
+                &[
+                    "--backend",
+                    "sftp",
+                    "--host",
+                    "nas",
+                    "--key-file",
+                    "/nonexistent",
+                ],
+                "key file does not exist",
+            ),
+            (
+                &["--backend", "sftp", "--host", "nas", "--user", ""],
+                "User must not be empty",
+            ),
+            (
+                &["--backend", "webdav", "--url", "ftp://h"],
+                "must start with http",
+            ),
+            (
+                &[
+                    "--backend",
+                    "webdav",
+                    "--url",
+                    "http://h",
+                    "--webdav-vendor",
+                    "box",
+                ],
+                "Unknown WebDAV vendor",
+            ),
+            (
+                &["--backend", "webdav", "--url", "http://h", "--password", ""],
+                "Password must not be empty",
+            ),
+        ] {
+            let err = remote(args).unwrap_err().to_string();
+            assert!(err.contains(message), "{:?}: {}", args, err);
+        }
+    }
+}
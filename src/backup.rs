@@ -0,0 +1,395 @@
+use crate::{
+    job::{Credentials, Job, Mode, Overlap, ARCHIVE_DIR},
+    paths::JobPaths,
+    rclone_log, shell,
+};
+use anyhow::{Context, Result};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, File},
+    io::{Read, Seek, SeekFrom},
+    path::Path,
+    process::{Command, Stdio},
+    time::{Duration, Instant},
+};
+
+/// Totals from rclone's final stats summary. Field names match the
+/// `stats` object of its JSON log.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RunStats {
+    pub bytes: u64,
+    pub checks: u64,
+    pub transfers: u64,
+    pub deletes: u64,
+    pub errors: u64,
+    /// How often rclone restarted the run after a failed attempt.
+    pub retries: u64,
+}
+
+/// Formats a byte count with binary units, e.g. `1.5 MiB`.
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+/// What happened when rclone ran for a job.
+#[derive(Debug)]
+pub struct BackupResult {
+    /// `None` if rclone was killed by a signal.
+    pub exit_code: Option<i32>,
+    pub duration: Duration,
+    pub stats: RunStats,
+    /// The first few errors rclone logged.
+    pub errors: Vec<String>,
+    /// Everything rclone printed to stdout and stderr; most of its output
+    /// goes to the job's log file instead.
+    pub output: String,
+}
+
+impl BackupResult {
+    pub fn success(&self) -> bool {
+        self.exit_code == Some(0)
+    }
+}
+
+/// The rclone arguments for one backup of `job`. The legacy backup script
+/// runs exactly the same command.
+pub fn rclone_args(job: &Job, log_file: &Path) -> Vec<String> {
+    let command = match job.mode {
+        Mode::Sync | Mode::Versioned => "sync",
+        Mode::Copy => "copy",
+    };
+    let mut args = vec![
+        command.to_string(),
+        job.source.clone(),
+        job.destination(),
+        format!("--log-file={}", log_file.display()),
+        "--log-level".to_string(),
+        "INFO".to_string(),
+        // One JSON object per line, read back by `rclone_log`, with a
+        // one-line stats summary every minute and at the end.
+        "--use-json-log".to_string(),
+        "--stats".to_string(),
+        "1m".to_string(),
+        "--stats-one-line".to_string(),
+        // Keeps sync from deleting archives left by an earlier versioned run.
+        "--filter".to_string(),
+        format!("- /{}/**", ARCHIVE_DIR),
+    ];
+    match job.mode {
+        Mode::Sync => args.push("--delete-during".to_string()),
+        Mode::Copy => {}
+        Mode::Versioned => {
+            args.push("--delete-during".to_string());
+            args.push("--backup-dir".to_string());
+            args.push(job.archive_dir(Utc::now()));
+        }
+    }
+    if let Some(max_delete) = job.max_delete {
+        args.push("--max-delete".to_string());
+        args.push(max_delete.to_string());
+    }
+    args
+}
+
+/// The legacy backup script for `job`: take the run lock with flock(1),
+/// load the rclone.conf password if needed, then run rclone.
+pub fn script_content(job: &Job, job_paths: &JobPaths) -> String {
+    let password_lines = match &job.config_password {
+        Some(source) if job.credentials == Credentials::Encrypted => source.script_lines(),
+        _ => String::new(),
+    };
+    // Share the lock file with `rcloneup run`; flock(1) cannot kill the
+    // previous run, so setup only allows skip and wait here.
+    let lock_file = shell::quote(&job_paths.lock_file.to_string_lossy());
+    let lock_lines = match job.on_overlap {
+        Overlap::Wait => format!(
+            "exec 9>>{}\nflock -w {} 9 || {{ echo \"Timed out waiting for the previous run\" >&2; exit 1; }}\n",
+            lock_file, job.lock_timeout
+        ),
+        _ => format!(
+            "exec 9>>{}\nflock -n 9 || {{ echo \"The previous run is still going, skipping this run.\"; exit 0; }}\n",
+            lock_file
+        ),
+    };
+    format!(
+        "#!/bin/bash\n{}{}{}\n",
+        lock_lines,
+        password_lines,
+        command_line(&rclone_args(job, &job_paths.log_file))
+    )
+}
+
+/// `rclone_args` as a shell command line.
+pub fn command_line(args: &[String]) -> String {
+    let mut line = "rclone".to_string();
+    for arg in args {
+        line.push(' ');
+        line.push_str(&shell::quote(arg));
+    }
+    line
+}
+
+/// An rclone command that can read `job`'s remotes, with the rclone.conf
+/// password in its environment when the file is encrypted.
+pub fn rclone_command(job: &Job) -> Result<Command> {
+    let mut command = Command::new("rclone");
+    if let Some(source) = &job.config_password {
+        command.env("RCLONE_CONFIG_PASS", source.read()?.expose());
+    }
+    Ok(command)
+}
+
+/// Runs rclone for `job` and waits for it to finish. `on_start` is given
+/// rclone's PID once it is running.
+pub fn run(
+    job: &Job,
+    job_paths: &JobPaths,
+    verbose: bool,
+    on_start: impl FnOnce(u32) -> Result<()>,
+) -> Result<BackupResult> {
+    let args = rclone_args(job, &job_paths.log_file);
+    if verbose {
+        println!("Running: {}", command_line(&args));
+    }
+
+    let mut command = rclone_command(job)?;
+    command.args(&args);
+
+    // Only the part of the log written by this run holds its stats.
+    let log_start = fs::metadata(&job_paths.log_file).map_or(0, |m| m.len());
+    let started = Instant::now();
+    let child = command
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("Failed to run rclone")?;
+    on_start(child.id())?;
+    let output = child
+        .wait_with_output()
+        .context("Failed to wait for rclone")?;
+    let duration = started.elapsed();
+    let events = rclone_log::parse(&read_from(&job_paths.log_file, log_start));
+    let summary = rclone_log::Summary::from_events(&events);
+
+    let mut text = String::from_utf8_lossy(&output.stdout).into_owned();
+    text.push_str(&String::from_utf8_lossy(&output.stderr));
+    Ok(BackupResult {
+        exit_code: output.status.code(),
+        duration,
+        stats: summary.stats,
+        errors: summary.errors,
+        output: text,
+    })
+}
+
+/// The text of `path` after byte `offset`, or nothing if it cannot be read.
+fn read_from(path: &Path, offset: u64) -> String {
+    let mut text = Vec::new();
+    if let Ok(mut file) = File::open(path) {
+        if file.seek(SeekFrom::Start(offset)).is_ok() {
+            let _ = file.read_to_end(&mut text);
+        }
+    }
+    String::from_utf8_lossy(&text).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        job::{PasswordSource, Runner, ARCHIVE_TIMESTAMP},
+        testing::{self, TempDir},
+    };
+    use std::{ffi::OsStr, io::Write, path::PathBuf};
+
+    const SOURCE: &str = "/data/it's \"a\" $HOME `id` 100%\\";
+
+    fn job(source: &str) -> Job {
+        Job {
+            runner: Runner::Script,
+            ..testing::job("default", source)
+        }
+    }
+
+    /// Paths below `home`, which need not exist.
+    fn job_paths(home: &str) -> JobPaths {
+        let home = PathBuf::from(home);
+        crate::paths::Paths {
+            rclone_config_dir: home.join("rclone"),
+            rclone_config_file: home.join("rclone/rclone.conf"),
+            jobs_dir: home.join("jobs"),
+            systemd_user_dir: home.join("systemd"),
+            recovery_dir: home.join("recovery"),
+            legacy_backup_script: home.join("backup.sh"),
+        }
+        .job("default")
+    }
+
+    /// Runs the script's rclone line with printf in place of rclone.
+    fn rclone_words(script: &str) -> Vec<String> {
+        let line = script.lines().last().unwrap();
+        let line = line.strip_prefix("rclone ").unwrap();
+        let output = Command::new("sh")
+            .arg("-c")
+            .arg(format!("printf '%s\\0' {}", line))
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        String::from_utf8(output.stdout)
+            .unwrap()
+            .split_terminator('\0')
+            .map(String::from)
+            .collect()
+    }
+
+    #[test]
+    fn script_passes_hostile_source_as_one_word() {
+        let job = job(SOURCE);
+        let paths = job_paths("/home/o'brien/$x");
+        let script = script_content(&job, &paths);
+        let words = rclone_words(&script);
+        assert_eq!(words, rclone_args(&job, &paths.log_file));
+        assert_eq!(words[1], SOURCE);
+        assert!(script.contains(&format!(
+            "exec 9>>{}\n",
+            shell::quote(&paths.lock_file.to_string_lossy())
+        )));
+    }
+
+    #[test]
+    fn script_is_valid_bash() {
+        let script = script_content(&job(SOURCE), &job_paths("/tmp/`id` $(id)"));
+        let status = Command::new("bash")
+            .args(["-n", "-c", &script])
+            .status()
+            .unwrap();
+        assert!(status.success());
+    }
+
+    #[test]
+    fn each_mode_runs_the_matching_rclone_command() {
+        let paths = job_paths("/home/u");
+        let flag = |args: &[String], flag: &str| args.iter().any(|a| a == flag);
+        let filter = format!("- /{}/**", ARCHIVE_DIR);
+
+        let sync = rclone_args(&job("/data"), &paths.log_file);
+        assert_eq!(sync[..3], ["sync", "/data", "minio:backups"]);
+        assert!(flag(&sync, "--delete-during"));
+        assert!(!flag(&sync, "--backup-dir"));
+
+        let copy = Job {
+            mode: Mode::Copy,
+            ..job("/data")
+        };
+        let copy = rclone_args(&copy, &paths.log_file);
+        assert_eq!(copy[..3], ["copy", "/data", "minio:backups"]);
+        assert!(!flag(&copy, "--delete-during"));
+        assert!(!flag(&copy, "--backup-dir"));
+
+        let versioned = Job {
+            mode: Mode::Versioned,
+            ..job("/data")
+        };
+        let before = Utc::now();
+        let versioned = rclone_args(&versioned, &paths.log_file);
+        assert_eq!(versioned[..3], ["sync", "/data", "minio:backups"]);
+        assert!(flag(&versioned, "--delete-during"));
+        let at = versioned.iter().position(|a| a == "--backup-dir").unwrap();
+        let prefix = format!("minio:backups/{}/", ARCHIVE_DIR);
+        let stamp = versioned[at + 1].strip_prefix(&prefix).unwrap();
+        let stamp = chrono::NaiveDateTime::parse_from_str(stamp, ARCHIVE_TIMESTAMP).unwrap();
+        assert!(stamp.and_utc() >= before - chrono::Duration::seconds(1));
+
+        for args in [&sync, &copy, &versioned] {
+            let at = args.iter().position(|a| a == "--filter").unwrap();
+            assert_eq!(args[at + 1], filter, "archives are never synced over");
+        }
+    }
+
+    #[test]
+    fn max_delete_is_passed_to_rclone() {
+        let paths = job_paths("/home/u");
+        let args = rclone_args(&job("/data"), &paths.log_file);
+        assert!(!args.contains(&"--max-delete".to_string()));
+
+        let job = Job {
+            max_delete: Some(25),
+            ..job("/data")
+        };
+        let args = rclone_args(&job, &paths.log_file);
+        assert!(args.ends_with(&["--max-delete".to_string(), "25".to_string()]));
+    }
+
+    #[test]
+    fn scheduled_command_runs_the_binary_or_the_script() {
+        let paths = job_paths("/home/u");
+        let binary = testing::job("default", "/data");
+        let command = binary.scheduled_command(&paths).unwrap();
+        assert_eq!(
+            command[0],
+            std::env::current_exe().unwrap().to_string_lossy()
+        );
+        assert_eq!(command[1..], ["run", "--job", "default"]);
+        assert_eq!(
+            job("/data").scheduled_command(&paths).unwrap(),
+            [paths.backup_script.to_string_lossy()]
+        );
+    }
+
+    #[test]
+    fn command_line_is_what_rclone_receives() {
+        let job = job(SOURCE);
+        let args = rclone_args(&job, &job_paths("/home/o'brien").log_file);
+        let line = command_line(&args);
+        assert_eq!(rclone_words(&format!("#!/bin/sh\n{}", line)), args);
+    }
+
+    #[test]
+    fn rclone_gets_the_config_password_in_its_environment() {
+        let mut job = job("/data");
+        assert_eq!(rclone_command(&job).unwrap().get_envs().count(), 0);
+
+        job.config_password = Some(PasswordSource::Command("echo pw-123".to_string()));
+        let command = rclone_command(&job).unwrap();
+        let envs: Vec<_> = command.get_envs().collect();
+        assert_eq!(
+            envs,
+            [(OsStr::new("RCLONE_CONFIG_PASS"), Some(OsStr::new("pw-123")))]
+        );
+    }
+
+    #[test]
+    fn only_this_runs_part_of_the_log_is_read() {
+        let dir = TempDir::new();
+        let log = dir.path().join("backup.log");
+        assert_eq!(read_from(&log, 0), "");
+        fs::write(&log, "earlier run\n").unwrap();
+        let start = fs::metadata(&log).unwrap().len();
+        let mut file = fs::OpenOptions::new().append(true).open(&log).unwrap();
+        file.write_all(b"this run\n").unwrap();
+        assert_eq!(read_from(&log, start), "this run\n");
+    }
+
+    #[test]
+    fn bytes_are_shown_with_binary_units() {
+        assert_eq!(format_bytes(0), "0 B");
+        assert_eq!(format_bytes(1023), "1023 B");
+        assert_eq!(format_bytes(1536), "1.5 KiB");
+        assert_eq!(format_bytes(3 << 20), "3.0 MiB");
+        assert_eq!(format_bytes(5 << 40), "5.0 TiB");
+        assert_eq!(format_bytes(2048 << 40), "2048.0 TiB");
+    }
+}
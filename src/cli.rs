@@ -0,0 +1,360 @@
+use crate::{
+    backend::Backend,
+    job::{Credentials, Mode, Overlap, Runner, Scheduler, DEFAULT_JOB},
+    log_rotation::{LogRotation, LogSize},
+    notify::{NotifyOn, Preset},
+    provider::Provider,
+    secret::Secret,
+};
+use clap::{Args, Parser, Subcommand};
+use std::path::PathBuf;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+pub struct Cli {
+    #[command(flatten)]
+    pub global: GlobalArgs,
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+// Flags accepted by every subcommand.
+#[derive(Args, Debug)]
+pub struct GlobalArgs {
+    /// Enable verbose logging
+    #[arg(long, short, global = true, default_value_t = false)]
+    pub verbose: bool,
+    /// Dry-run mode (show actions without making changes)
+    #[arg(long, short, global = true, default_value_t = false)]
+    pub dry_run: bool,
+    /// Print access keys, passwords and other secrets instead of masking them
+    #[arg(long, global = true, default_value_t = false)]
+    pub show_secrets: bool,
+    /// Path to the rcloneup.toml setup reads (default: $XDG_CONFIG_HOME/rcloneup/ then /etc/rcloneup/)
+    #[arg(long, global = true, env = "RCLONEUP_CONFIG")]
+    pub config: Option<PathBuf>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Write the rclone config, backup script and cron job
+    Setup(Box<SetupArgs>),
+    /// List configured backup jobs
+    List,
+    /// Show each job's schedule, last run and whether its files match the setup
+    Status(StatusArgs),
+    /// Run a job's backup now
+    Run(JobArgs),
+    /// Remove everything setup created for one or every job
+    Uninstall(UninstallArgs),
+    /// Copy a job's remote bucket back to a local directory
+    Restore(RestoreArgs),
+    /// Delete a versioned job's archives that its retention policy expires
+    Prune(JobArgs),
+    /// Check a job's notifications
+    Notify(NotifyArgs),
+}
+
+// Every setup value is optional here so that rcloneup.toml can fill in
+// whatever was not given on the command line or in the environment.
+#[derive(Args, Debug)]
+pub struct SetupArgs {
+    /// Name of the backup job to create or update (default: every job in rcloneup.toml)
+    #[arg(long, env = "RCLONEUP_JOB")]
+    pub job: Option<String>,
+    /// Local directory to back up
+    #[arg(long, env = "BACKUP_SOURCE")]
+    pub source: Option<String>,
+    /// Rclone remote name [default: minio, or the backend name]
+    #[arg(long, env = "RCLONE_REMOTE")]
+    pub remote: Option<String>,
+    /// Remote bucket/container name, or the path on sftp, webdav and local remotes
+    #[arg(long, env = "REMOTE_BUCKET")]
+    pub bucket: Option<String>,
+    /// Kind of remote to back up to [default: s3]
+    #[arg(long, value_enum, env = "RCLONEUP_BACKEND")]
+    pub backend: Option<Backend>,
+    /// S3 service the remote talks to [default: minio]
+    #[arg(long, value_enum, env = "RCLONEUP_PROVIDER")]
+    pub provider: Option<Provider>,
+    /// S3 endpoint URL [default: depends on --provider, http://localhost:9000 for MinIO]
+    #[arg(long, env = "MINIO_ENDPOINT")]
+    pub endpoint: Option<String>,
+    /// S3 region (required for aws, b2 and digitalocean)
+    #[arg(long, env = "S3_REGION")]
+    pub region: Option<String>,
+    /// Cloudflare account ID (required for r2)
+    #[arg(long, env = "R2_ACCOUNT_ID")]
+    pub account_id: Option<String>,
+    /// Region new AWS buckets are created in
+    #[arg(long, env = "S3_LOCATION_CONSTRAINT")]
+    pub location_constraint: Option<String>,
+    /// S3 access key ID (required)
+    #[arg(long, env = "MINIO_ACCESS_KEY", hide_env_values = true)]
+    pub access_key: Option<Secret>,
+    /// S3 secret access key (required)
+    #[arg(long, env = "MINIO_SECRET_KEY", hide_env_values = true)]
+    pub secret_key: Option<Secret>,
+    /// SFTP server host name (required for sftp)
+    #[arg(long, env = "SFTP_HOST")]
+    pub host: Option<String>,
+    /// SFTP server port [default: 22]
+    #[arg(long, env = "SFTP_PORT")]
+    pub port: Option<u16>,
+    /// SFTP private key file [default: ssh-agent]
+    #[arg(long, env = "SFTP_KEY_FILE")]
+    pub key_file: Option<String>,
+    /// WebDAV URL (required for webdav)
+    #[arg(long, env = "WEBDAV_URL")]
+    pub url: Option<String>,
+    /// WebDAV server software, e.g. nextcloud [default: other]
+    #[arg(long, env = "WEBDAV_VENDOR")]
+    pub webdav_vendor: Option<String>,
+    /// User name for sftp and webdav
+    #[arg(long, env = "REMOTE_USER")]
+    pub user: Option<String>,
+    /// Password for sftp and webdav
+    #[arg(long, env = "REMOTE_PASSWORD", hide_env_values = true)]
+    pub password: Option<Secret>,
+    /// Encrypt backups client-side through an rclone crypt remote
+    #[arg(long)]
+    pub encrypt: bool,
+    /// Password for --encrypt [default: generated]
+    #[arg(long, env = "CRYPT_PASSWORD", hide_env_values = true)]
+    pub crypt_password: Option<Secret>,
+    /// Salt for --encrypt [default: generated]
+    #[arg(long, env = "CRYPT_SALT", hide_env_values = true)]
+    pub crypt_salt: Option<Secret>,
+    /// Cron schedule expression [default: "0 * * * *" (hourly)]
+    #[arg(long, env = "CRON_SCHEDULE")]
+    pub cron: Option<String>,
+    /// How backups are triggered [default: cron]
+    #[arg(long, value_enum, env = "RCLONEUP_SCHEDULER")]
+    pub scheduler: Option<Scheduler>,
+    /// How files reach the remote [default: sync]
+    #[arg(long, value_enum, env = "RCLONEUP_MODE")]
+    pub mode: Option<Mode>,
+    /// With --mode versioned, keep the N newest archives
+    #[arg(long, value_name = "N", env = "RCLONEUP_KEEP_LAST")]
+    pub keep_last: Option<u32>,
+    /// With --mode versioned, keep the newest archive of each of the last N days
+    #[arg(long, value_name = "N", env = "RCLONEUP_KEEP_DAILY")]
+    pub keep_daily: Option<u32>,
+    /// With --mode versioned, keep the newest archive of each of the last N weeks
+    #[arg(long, value_name = "N", env = "RCLONEUP_KEEP_WEEKLY")]
+    pub keep_weekly: Option<u32>,
+    /// With --mode versioned, keep the newest archive of each of the last N months
+    #[arg(long, value_name = "N", env = "RCLONEUP_KEEP_MONTHLY")]
+    pub keep_monthly: Option<u32>,
+    /// With --mode versioned, delete archives older than this many days
+    #[arg(long, value_name = "DAYS", env = "RCLONEUP_MAX_AGE")]
+    pub max_age: Option<u32>,
+    /// What the scheduler starts [default: binary]
+    #[arg(long, value_enum, env = "RCLONEUP_RUNNER")]
+    pub runner: Option<Runner>,
+    /// What to do when the previous run is still going [default: skip]
+    #[arg(long, value_enum, env = "RCLONEUP_ON_OVERLAP")]
+    pub on_overlap: Option<Overlap>,
+    /// Seconds to wait for the previous run with --on-overlap wait [default: 3600]
+    #[arg(long, value_name = "SECONDS", env = "RCLONEUP_LOCK_TIMEOUT")]
+    pub lock_timeout: Option<u64>,
+    /// Refuse to run unless the source is a mountpoint
+    #[arg(long)]
+    pub require_mountpoint: bool,
+    /// Refuse to run unless this file exists below the source
+    #[arg(long, value_name = "FILE", env = "RCLONEUP_SENTINEL")]
+    pub sentinel: Option<String>,
+    /// Refuse to run if the source lost more than this percentage of its files since the last run
+    #[arg(long, value_name = "PERCENT", env = "RCLONEUP_MAX_DROP")]
+    pub max_drop: Option<u32>,
+    /// Stop rclone from deleting more than N files on the remote in one run
+    #[arg(long, value_name = "N", env = "RCLONEUP_MAX_DELETE")]
+    pub max_delete: Option<u64>,
+    /// Who rotates the job's backup.log [default: rotate, off with --runner script]
+    #[arg(long, value_enum, env = "RCLONEUP_LOG_ROTATION")]
+    pub log_rotation: Option<LogRotation>,
+    /// Rotate backup.log once it reaches this size, e.g. 512k, 10M or 1G [default: 10M]
+    #[arg(long, value_name = "SIZE", env = "RCLONEUP_LOG_MAX_SIZE")]
+    pub log_max_size: Option<LogSize>,
+    /// Also rotate backup.log once it is older than this many days
+    #[arg(long, value_name = "DAYS", env = "RCLONEUP_LOG_MAX_AGE")]
+    pub log_max_age: Option<u32>,
+    /// How many compressed old logs to keep [default: 5]
+    #[arg(long, value_name = "N", env = "RCLONEUP_LOG_KEEP")]
+    pub log_keep: Option<u32>,
+    /// URL to notify after runs, see --notify-preset and --notify-on
+    #[arg(long, value_name = "URL", env = "RCLONEUP_NOTIFY_URL")]
+    pub notify_url: Option<Secret>,
+    /// Service behind --notify-url [default: webhook]
+    #[arg(long, value_enum, env = "RCLONEUP_NOTIFY_PRESET")]
+    pub notify_preset: Option<Preset>,
+    /// Which runs to notify about [default: failure]
+    #[arg(long, value_enum, env = "RCLONEUP_NOTIFY_ON")]
+    pub notify_on: Option<NotifyOn>,
+    /// Request body with {{job}}, {{outcome}}, {{message}} and other placeholders, replacing the preset's
+    #[arg(long, value_name = "TEMPLATE", env = "RCLONEUP_NOTIFY_TEMPLATE")]
+    pub notify_template: Option<String>,
+    /// How to store credentials in rclone.conf [default: plain]
+    #[arg(long, value_enum, env = "RCLONEUP_CREDENTIALS")]
+    pub credentials: Option<Credentials>,
+    /// Owner-only file holding the rclone.conf password (with --credentials encrypted)
+    #[arg(
+        long,
+        env = "RCLONEUP_CONFIG_PASSWORD_FILE",
+        conflicts_with = "config_password_command"
+    )]
+    pub config_password_file: Option<String>,
+    /// Shell command printing the rclone.conf password (with --credentials encrypted)
+    #[arg(long, env = "RCLONEUP_CONFIG_PASSWORD_COMMAND")]
+    pub config_password_command: Option<String>,
+    /// Print the next N times each job's schedule fires, in local time
+    #[arg(long, value_name = "N")]
+    pub show_next: Option<usize>,
+}
+
+#[derive(Args, Debug)]
+pub struct JobArgs {
+    /// Name of the backup job
+    #[arg(long, default_value = DEFAULT_JOB, env = "RCLONEUP_JOB")]
+    pub job: String,
+}
+
+#[derive(Args, Debug)]
+pub struct NotifyArgs {
+    #[command(subcommand)]
+    pub command: NotifyCommand,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum NotifyCommand {
+    /// Send a made-up failure (or success) notification for a job
+    Test(NotifyTestArgs),
+}
+
+#[derive(Args, Debug)]
+pub struct NotifyTestArgs {
+    /// Name of the backup job
+    #[arg(long, default_value = DEFAULT_JOB, env = "RCLONEUP_JOB")]
+    pub job: String,
+    /// Send a success notification instead of a failure
+    #[arg(long, default_value_t = false)]
+    pub success: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct StatusArgs {
+    /// Only show this job (default: every job)
+    #[arg(long)]
+    pub job: Option<String>,
+    /// Print the status as JSON for monitoring scripts
+    #[arg(long, default_value_t = false)]
+    pub json: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct UninstallArgs {
+    /// Only act on this job (default: every job)
+    #[arg(long)]
+    pub job: Option<String>,
+    /// Leave the job's rclone.conf sections in place
+    #[arg(long, default_value_t = false)]
+    pub keep_config: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct RestoreArgs {
+    /// Name of the backup job to restore
+    #[arg(long, default_value = DEFAULT_JOB, env = "RCLONEUP_JOB")]
+    pub job: String,
+    /// Local directory to restore into
+    #[arg(long)]
+    pub to: String,
+    /// Restore the state at this local time instead of the latest backup (versioned jobs)
+    #[arg(long, value_name = "TIME")]
+    pub at: Option<String>,
+    /// Only restore files matching this rclone filter pattern (repeatable)
+    #[arg(long, value_name = "PATTERN")]
+    pub include: Vec<String>,
+    /// Restore even if the target directory is not empty
+    #[arg(long, default_value_t = false)]
+    pub force: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    fn parse(args: &[&str]) -> Result<Cli, clap::Error> {
+        Cli::try_parse_from(["rcloneup"].iter().chain(args))
+    }
+
+    #[test]
+    fn cli_definition_is_consistent() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn subcommands_take_their_own_flags_and_global_ones_anywhere() {
+        let cli = parse(&["status", "--json", "--job", "photos", "-v"]).unwrap();
+        assert!(cli.global.verbose);
+        let Command::Status(args) = cli.command else {
+            panic!("not status: {:?}", cli.command);
+        };
+        assert!(args.json);
+        assert_eq!(args.job.as_deref(), Some("photos"));
+
+        let cli = parse(&["--dry-run", "uninstall", "--keep-config"]).unwrap();
+        assert!(cli.global.dry_run);
+        let Command::Uninstall(args) = cli.command else {
+            panic!("not uninstall: {:?}", cli.command);
+        };
+        assert!(args.keep_config);
+        assert_eq!(args.job, None);
+
+        let cli = parse(&[
+            "restore",
+            "--to",
+            "/tmp/r",
+            "--include",
+            "a/**",
+            "--include",
+            "b",
+        ])
+        .unwrap();
+        let Command::Restore(args) = cli.command else {
+            panic!("not restore: {:?}", cli.command);
+        };
+        assert_eq!(args.include, ["a/**", "b"]);
+        assert!(!args.force);
+    }
+
+    #[test]
+    fn run_defaults_to_the_default_job() {
+        let Command::Run(args) = parse(&["run"]).unwrap().command else {
+            panic!("not run");
+        };
+        assert_eq!(args.job, DEFAULT_JOB);
+    }
+
+    #[test]
+    fn bad_command_lines_are_rejected() {
+        for args in [
+            &[][..],
+            &["backup"],
+            &["restore"],
+            &["status", "--source", "/data"],
+            &["setup", "--mode", "mirror"],
+            &[
+                "setup",
+                "--config-password-file",
+                "f",
+                "--config-password-command",
+                "c",
+            ],
+            &["notify"],
+        ] {
+            assert!(parse(args).is_err(), "accepted {:?}", args);
+        }
+    }
+}
@@ -0,0 +1,27 @@
+use crate::{cli::GlobalArgs, job::Job, paths::Paths};
+use anyhow::Result;
+
+pub fn list(global: &GlobalArgs) -> Result<()> {
+    let paths = Paths::resolve()?;
+    if global.verbose {
+        paths.print();
+    }
+
+    let jobs = Job::list(&paths)?;
+    if jobs.is_empty() {
+        println!("No backup jobs configured (run 'rcloneup setup' to create one).");
+        return Ok(());
+    }
+    for job in &jobs {
+        let encrypted = if job.crypt_remote.is_some() {
+            ", encrypted"
+        } else {
+            ""
+        };
+        println!(
+            "{}: {} -> {}:{} ({} via {}{})",
+            job.name, job.source, job.remote, job.bucket, job.cron, job.scheduler, encrypted
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,19 @@
+use crate::{job::Job, paths::Paths};
+use anyhow::Result;
+
+pub mod list;
+pub mod notify;
+pub mod prune;
+pub mod restore;
+pub mod run;
+pub mod setup;
+pub mod status;
+pub mod uninstall;
+
+/// Loads the job named by `--job`, or every job when none was given.
+fn select_jobs(paths: &Paths, name: Option<&str>) -> Result<Vec<Job>> {
+    match name {
+        Some(name) => Ok(vec![Job::load(paths, name)?]),
+        None => Job::list(paths),
+    }
+}
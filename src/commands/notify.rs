@@ -0,0 +1,44 @@
+use crate::{
+    cli::{GlobalArgs, NotifyArgs, NotifyCommand},
+    job::Job,
+    notify::{self, url_host},
+    paths::Paths,
+};
+use anyhow::{bail, Result};
+
+pub fn notify(args: &NotifyArgs, global: &GlobalArgs) -> Result<()> {
+    match &args.command {
+        NotifyCommand::Test(args) => {
+            let job = Job::load(&Paths::resolve()?, &args.job)?;
+            let Some(notify) = &job.notify else {
+                bail!(
+                    "Job '{}' has no notifications (see 'rcloneup setup --notify-url')",
+                    job.name
+                );
+            };
+            let record = notify::sample_record(&job.name, args.success);
+            if global.dry_run {
+                println!(
+                    "(dry-run) Would send a test {} notification to {}",
+                    notify.preset,
+                    url_host(notify.url.expose())
+                );
+                if global.verbose {
+                    println!("--- request body ---\n{}", notify.body(&record, false));
+                }
+                return Ok(());
+            }
+            if global.verbose {
+                println!("Request body: {}", notify.body(&record, false));
+            }
+            notify.send(&record, false)?;
+            println!(
+                "Sent a test {} notification for job '{}' to {}",
+                notify.preset,
+                job.name,
+                url_host(notify.url.expose())
+            );
+            Ok(())
+        }
+    }
+}
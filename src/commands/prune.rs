@@ -0,0 +1,32 @@
+use crate::{
+    cli::{GlobalArgs, JobArgs},
+    job::{Job, Mode},
+    paths::Paths,
+    retention,
+};
+use anyhow::{bail, Result};
+
+pub fn prune(args: &JobArgs, global: &GlobalArgs) -> Result<()> {
+    let job = Job::load(&Paths::resolve()?, &args.job)?;
+    if job.mode != Mode::Versioned {
+        bail!("Job '{}' is not versioned, it has no archives", job.name);
+    }
+    if job.retention.is_empty() {
+        println!(
+            "Job '{}' has no retention policy, keeping every archive.",
+            job.name
+        );
+        return Ok(());
+    }
+    if !crate::is_rclone_installed()? {
+        bail!("'rclone' not found in PATH. Please install it before pruning.");
+    }
+
+    let count = retention::prune(&job, global.dry_run, global.verbose)?;
+    match (count, global.dry_run) {
+        (0, _) => println!("No archives of job '{}' have expired.", job.name),
+        (n, true) => println!("(dry-run) Would delete {} archive(s).", n),
+        (n, false) => println!("Deleted {} archive(s).", n),
+    }
+    Ok(())
+}
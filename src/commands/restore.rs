@@ -0,0 +1,417 @@
+use crate::{
+    backup,
+    cli::{GlobalArgs, RestoreArgs},
+    job::{Job, Mode, ARCHIVE_DIR},
+    paths::Paths,
+    rclone_log::{self, Event},
+    retention::{self, Archive},
+    shell,
+};
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use std::{
+    collections::HashSet,
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    os::unix::fs::OpenOptionsExt,
+    path::Path,
+    process,
+};
+
+/// Formats accepted by `--at`, in local time.
+const AT_FORMATS: [&str; 4] = [
+    "%Y-%m-%dT%H:%M:%S",
+    "%Y-%m-%dT%H:%M",
+    "%Y-%m-%d %H:%M:%S",
+    "%Y-%m-%d %H:%M",
+];
+
+pub fn restore(args: &RestoreArgs, global: &GlobalArgs) -> Result<()> {
+    let target = Path::new(&args.to);
+    if !args.force && target.exists() && fs::read_dir(target)?.next().is_some() {
+        bail!(
+            "Restore target is not empty: {} (use --force to restore anyway)",
+            args.to
+        );
+    }
+    for pattern in &args.include {
+        shell::reject_control_chars("Include pattern", pattern)?;
+    }
+    let at = args.at.as_deref().map(parse_at).transpose()?;
+
+    let paths = Paths::resolve()?;
+    let job = Job::load(&paths, &args.job)?;
+    if global.dry_run && at.is_none() {
+        println!(
+            "(dry-run) Would restore {} into {}",
+            job.destination(),
+            args.to
+        );
+        return Ok(());
+    }
+    if !crate::is_rclone_installed()? {
+        bail!("'rclone' not found in PATH. Please install it before restoring.");
+    }
+
+    // A file's state at `at` is the copy that the first run after `at`
+    // moved into its archive, or the current copy if no later run touched
+    // it. Each file is therefore taken from the oldest of these layers
+    // that has it: the archives newer than `at`, then the current backup.
+    // Files modified after `at` did not exist in that state yet, so every
+    // layer skips them by their modification time.
+    let mut layers = Vec::new();
+    if let Some(at) = at {
+        println!(
+            "Note: files are restored as of {} by their modification time; files created later but with an older modification time (e.g. copied with 'cp -p') are restored too.",
+            at.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S")
+        );
+        let archives = retention::list_archives(&job)?;
+        if archives.is_empty() && job.mode != Mode::Versioned {
+            bail!(
+                "Job '{}' has no archives to restore an earlier state from (see --mode versioned)",
+                job.name
+            );
+        }
+        if archives.first().is_some_and(|oldest| oldest.time > at) {
+            println!(
+                "Warning: the oldest archive of job '{}' is from {}; files deleted before then cannot be restored.",
+                job.name,
+                archives[0].time.with_timezone(&Local).format("%Y-%m-%d %H:%M")
+            );
+        }
+        layers.extend(archive_layers(&job, &archives, at));
+    }
+    layers.push(job.destination());
+
+    if global.dry_run {
+        for layer in &layers {
+            println!("(dry-run) Would restore {} into {}", layer, args.to);
+        }
+        return Ok(());
+    }
+
+    fs::create_dir_all(target)
+        .with_context(|| format!("Failed to create restore directory {:?}", target))?;
+
+    let mut restored = Vec::new();
+    if let [layer] = layers.as_slice() {
+        if global.verbose {
+            println!("Restoring {} into {}", layer, args.to);
+        }
+        restored = copy_layer(&job, layer, target, &args.include, at, None)?;
+    } else {
+        // Lives in the job's owner-only directory, not a shared /tmp.
+        let list_file = paths
+            .job(&job.name)
+            .dir
+            .join(format!("restore-{}.files", process::id()));
+        let mut taken = HashSet::new();
+        for layer in &layers {
+            let files: Vec<String> = list_files(&job, layer, &args.include, at)?
+                .into_iter()
+                .filter(|file| !taken.contains(file))
+                .collect();
+            if files.is_empty() {
+                continue;
+            }
+            if global.verbose {
+                println!(
+                    "Restoring {} file(s) from {} into {}",
+                    files.len(),
+                    layer,
+                    args.to
+                );
+            }
+            restored.extend(copy_layer(
+                &job,
+                layer,
+                target,
+                &args.include,
+                at,
+                Some((&files, &list_file)),
+            )?);
+            taken.extend(files);
+        }
+    }
+
+    let bytes: u64 = restored
+        .iter()
+        .filter_map(|path| fs::metadata(target.join(path)).ok())
+        .map(|metadata| metadata.len())
+        .sum();
+    println!(
+        "Restore complete! {} file(s), {} restored into {}",
+        restored.len(),
+        backup::format_bytes(bytes),
+        args.to
+    );
+    Ok(())
+}
+
+/// The archives, oldest first, that hold the state at `at` of files that
+/// changed since.
+fn archive_layers(job: &Job, archives: &[Archive], at: DateTime<Utc>) -> Vec<String> {
+    archives
+        .iter()
+        .filter(|archive| archive.time > at)
+        .map(|archive| job.destination_path(&format!("{}/{}", ARCHIVE_DIR, archive.name)))
+        .collect()
+}
+
+/// The files below `source` that match `include` and, with `at`, were
+/// last modified by then.
+fn list_files(
+    job: &Job,
+    source: &str,
+    include: &[String],
+    at: Option<DateTime<Utc>>,
+) -> Result<Vec<String>> {
+    let mut command = backup::rclone_command(job)?;
+    command
+        .arg("lsf")
+        .arg(source)
+        .arg("--recursive")
+        .arg("--files-only")
+        .args(filter_args(include, at));
+    let output = command.output().context("Failed to run rclone lsf")?;
+    if !output.status.success() {
+        bail!(
+            "rclone lsf {} failed with {}: {}",
+            source,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(String::from)
+        .collect())
+}
+
+/// Copies `source` into `target`, or only `files` of it, listed in the
+/// given file for rclone, and returns the paths rclone reported as copied.
+fn copy_layer(
+    job: &Job,
+    source: &str,
+    target: &Path,
+    include: &[String],
+    at: Option<DateTime<Utc>>,
+    files: Option<(&[String], &Path)>,
+) -> Result<Vec<String>> {
+    let mut command = backup::rclone_command(job)?;
+    command
+        .arg("copy")
+        .arg(source)
+        .arg(target)
+        .arg("--log-level")
+        .arg("INFO")
+        .arg("--use-json-log")
+        .arg("--stats")
+        .arg("0");
+    let list = match files {
+        Some((files, list)) => {
+            write_file_list(list, files)?;
+            command.arg("--files-from-raw").arg(list);
+            Some(list)
+        }
+        None => {
+            command.args(filter_args(include, at));
+            None
+        }
+    };
+
+    let output = command.output().context("Failed to run rclone");
+    if let Some(list) = list {
+        let _ = fs::remove_file(list);
+    }
+    let output = output?;
+    let log = String::from_utf8_lossy(&output.stderr);
+    if !output.status.success() {
+        eprint!("{}", log);
+        bail!("rclone copy from {} failed with {}", source, output.status);
+    }
+    Ok(rclone_log::parse(&log)
+        .into_iter()
+        .filter_map(|event| match event {
+            Event::Transferred { path } => Some(path),
+            _ => None,
+        })
+        .collect())
+}
+
+/// Writes `files` to `path`, one per line, into a file only the owner can
+/// read. A leftover list from an earlier run with the same PID is replaced.
+fn write_file_list(path: &Path, files: &[String]) -> Result<()> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => {
+            return Err(e).with_context(|| format!("Failed to remove {}", path.display()))
+        }
+        _ => {}
+    }
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(path)
+        .with_context(|| format!("Failed to create {}", path.display()))?;
+    file.write_all((files.join("\n") + "\n").as_bytes())
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(())
+}
+
+/// Filter rules that skip the archives and, if given, everything that
+/// does not match one of the `include` patterns or was modified after `at`.
+fn filter_args(include: &[String], at: Option<DateTime<Utc>>) -> Vec<String> {
+    let mut args = vec!["--filter".to_string(), format!("- /{}/**", ARCHIVE_DIR)];
+    if let Some(at) = at {
+        // rclone takes an absolute time here too: only files older than it.
+        args.push("--min-age".to_string());
+        args.push(at.format("%Y-%m-%dT%H:%M:%SZ").to_string());
+    }
+    for pattern in include {
+        args.push("--filter".to_string());
+        args.push(format!("+ {}", pattern));
+    }
+    if !include.is_empty() {
+        args.push("--filter".to_string());
+        args.push("- **".to_string());
+    }
+    args
+}
+
+/// Parses `--at` as local time, or as the end of the day for a bare date.
+fn parse_at(value: &str) -> Result<DateTime<Utc>> {
+    let naive = AT_FORMATS
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+        .or_else(|| {
+            NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                .ok()
+                .and_then(|date| date.and_hms_opt(23, 59, 59))
+        });
+    let Some(naive) = naive else {
+        bail!(
+            "Invalid --at '{}', expected a local time like 2026-10-01T12:00 or a date like 2026-10-01",
+            value
+        );
+    };
+    match Local.from_local_datetime(&naive).earliest() {
+        Some(local) => Ok(local.with_timezone(&Utc)),
+        None => bail!("--at '{}' does not exist in the local time zone", value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, TempDir};
+    use std::os::unix::fs::PermissionsExt;
+
+    fn local(value: &str) -> DateTime<Utc> {
+        let naive = NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S").unwrap();
+        Local
+            .from_local_datetime(&naive)
+            .earliest()
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    #[test]
+    fn at_is_local_time_or_the_end_of_a_day() {
+        for value in [
+            "2026-03-09T07:05:30",
+            "2026-03-09 07:05:30",
+            "2026-03-09T07:05",
+            "2026-03-09 07:05",
+        ] {
+            let seconds = if value.len() == 16 { ":00" } else { "" };
+            let expected = local(&format!("{}{}", value.replace('T', " "), seconds));
+            assert_eq!(parse_at(value).unwrap(), expected, "{}", value);
+        }
+        assert_eq!(
+            parse_at("2026-03-09").unwrap(),
+            local("2026-03-09 23:59:59")
+        );
+        for value in [
+            "",
+            "yesterday",
+            "2026-13-01",
+            "2026-03-09T25:00",
+            "09.03.2026",
+        ] {
+            let err = parse_at(value).unwrap_err();
+            assert!(err.to_string().starts_with("Invalid --at"), "{}", err);
+        }
+    }
+
+    #[test]
+    fn restore_takes_archives_from_after_at() {
+        let job = testing::job("web", "/data");
+        let archives: Vec<Archive> = [
+            "2026-03-01T000000Z",
+            "2026-03-05T120000Z",
+            "2026-03-09T000000Z",
+        ]
+        .into_iter()
+        .map(|name| Archive {
+            name: name.to_string(),
+            time: NaiveDateTime::parse_from_str(name, crate::job::ARCHIVE_TIMESTAMP)
+                .unwrap()
+                .and_utc(),
+        })
+        .collect();
+        let at = |value: &str| value.parse::<DateTime<Utc>>().unwrap();
+
+        assert_eq!(
+            archive_layers(&job, &archives, at("2026-03-05T12:00:00Z")),
+            ["minio:backups/.rcloneup-archive/2026-03-09T000000Z"]
+        );
+        assert_eq!(
+            archive_layers(&job, &archives, at("2026-02-01T00:00:00Z")),
+            [
+                "minio:backups/.rcloneup-archive/2026-03-01T000000Z",
+                "minio:backups/.rcloneup-archive/2026-03-05T120000Z",
+                "minio:backups/.rcloneup-archive/2026-03-09T000000Z",
+            ]
+        );
+        assert!(archive_layers(&job, &archives, at("2026-03-10T00:00:00Z")).is_empty());
+    }
+
+    #[test]
+    fn filters_skip_archives_newer_files_and_everything_not_included() {
+        assert_eq!(
+            filter_args(&[], None),
+            ["--filter", "- /.rcloneup-archive/**"]
+        );
+        let at = "2026-03-09T07:05:00Z".parse().unwrap();
+        let include = ["docs/**".to_string(), "*.txt".to_string()];
+        assert_eq!(
+            filter_args(&include, Some(at)),
+            [
+                "--filter",
+                "- /.rcloneup-archive/**",
+                "--min-age",
+                "2026-03-09T07:05:00Z",
+                "--filter",
+                "+ docs/**",
+                "--filter",
+                "+ *.txt",
+                "--filter",
+                "- **",
+            ]
+        );
+    }
+
+    #[test]
+    fn file_list_is_private_and_replaces_a_leftover() {
+        let dir = TempDir::new();
+        let list = dir.path().join("restore-1.files");
+        fs::write(&list, "stale\n").unwrap();
+        fs::set_permissions(&list, fs::Permissions::from_mode(0o644)).unwrap();
+        let files = ["a.txt".to_string(), "dir/it's here".to_string()];
+        write_file_list(&list, &files).unwrap();
+        assert_eq!(fs::read_to_string(&list).unwrap(), "a.txt\ndir/it's here\n");
+        let mode = fs::metadata(&list).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+}
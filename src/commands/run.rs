@@ -0,0 +1,150 @@
+use crate::{
+    backup::{self, RunStats},
+    cli::{GlobalArgs, JobArgs},
+    guard::{self, GuardError},
+    job::{Job, Mode},
+    lock::RunLock,
+    log_rotation, notify,
+    paths::{JobPaths, Paths},
+    retention,
+    state::{self, Outcome, RunRecord},
+};
+use anyhow::{bail, Result};
+use chrono::Utc;
+use std::time::Duration;
+
+pub fn run(args: &JobArgs, global: &GlobalArgs) -> Result<()> {
+    let paths = Paths::resolve()?;
+    let job = Job::load(&paths, &args.job)?;
+    let job_paths = paths.job(&job.name);
+
+    if global.dry_run {
+        println!(
+            "(dry-run) Would run: {}",
+            backup::command_line(&backup::rclone_args(&job, &job_paths.log_file))
+        );
+        if job.mode == Mode::Versioned && !job.retention.is_empty() && crate::is_rclone_installed()?
+        {
+            retention::prune(&job, true, global.verbose)?;
+        }
+        return Ok(());
+    }
+
+    if !crate::is_rclone_installed()? {
+        bail!("'rclone' not found in PATH. Please install it before running a backup.");
+    }
+
+    let mut record = RunRecord {
+        job: job.name.clone(),
+        started: Utc::now(),
+        finished: Utc::now(),
+        outcome: Outcome::Success,
+        exit_code: None,
+        stats: RunStats::default(),
+        rclone_errors: Vec::new(),
+        error: None,
+        prune_error: None,
+    };
+    let result = attempt(&job, &job_paths, global, &mut record);
+    if let Err(e) = &result {
+        record.outcome = if e.downcast_ref::<GuardError>().is_some() {
+            Outcome::Refused
+        } else {
+            Outcome::Failed
+        };
+        record.error = Some(format!("{:#}", e));
+    }
+    record.finished = Utc::now();
+    // Read before appending, so that a recovery can be told apart.
+    let previous = state::load(&job_paths).ok().and_then(|runs| {
+        runs.into_iter()
+            .rev()
+            .find(|run| run.outcome != Outcome::Skipped)
+    });
+    // The run's own result matters more than a history that failed to save.
+    if let Err(e) = state::append(&job_paths, &record) {
+        eprintln!(
+            "Warning: failed to record the run of job '{}': {:#}",
+            job.name, e
+        );
+    }
+    if let Some(notify) = &job.notify {
+        notify::after_run(notify, &record, previous.as_ref(), global.verbose);
+    }
+    result
+}
+
+/// Runs the backup itself, filling in `record` as it goes.
+fn attempt(
+    job: &Job,
+    job_paths: &JobPaths,
+    global: &GlobalArgs,
+    record: &mut RunRecord,
+) -> Result<()> {
+    let Some(mut lock) = RunLock::acquire(
+        &job_paths.lock_file,
+        job.on_overlap,
+        Duration::from_secs(job.lock_timeout),
+        global.verbose,
+    )?
+    else {
+        record.outcome = Outcome::Skipped;
+        return Ok(());
+    };
+    // Under the lock, so no other run is writing the log.
+    if let Err(e) = log_rotation::rotate_if_due(job_paths, &job.logs, global.verbose) {
+        eprintln!(
+            "Warning: failed to rotate {}: {:#}",
+            job_paths.log_file.display(),
+            e
+        );
+    }
+    let file_count = guard::check(job, job_paths)?;
+    let result = backup::run(job, job_paths, global.verbose, |pid| lock.record_child(pid))?;
+    record.exit_code = result.exit_code;
+    record.stats = result.stats;
+    record.rclone_errors = result.errors.clone();
+    let output = result.output.trim_end();
+    if !output.is_empty() && (global.verbose || !result.success()) {
+        println!("{}", output);
+    }
+    if !result.success() {
+        for error in &result.errors {
+            println!("rclone error: {}", error);
+        }
+        let exit = match result.exit_code {
+            Some(code) => format!("exit code {}", code),
+            None => "a signal".to_string(),
+        };
+        bail!(
+            "Backup of job '{}' failed with {} after {:.1}s (see {})",
+            job.name,
+            exit,
+            result.duration.as_secs_f64(),
+            job_paths.log_file.display()
+        );
+    }
+
+    if let Some(count) = file_count {
+        guard::record_file_count(job_paths, count)?;
+    }
+    println!(
+        "Backup of job '{}' complete in {:.1}s!",
+        job.name,
+        result.duration.as_secs_f64()
+    );
+
+    if job.mode == Mode::Versioned && !job.retention.is_empty() {
+        // Still under the lock, so the next run cannot start mid-prune.
+        // The backup itself is done, so a failed prune only warns.
+        if let Err(e) = retention::prune(job, false, global.verbose) {
+            eprintln!(
+                "Warning: failed to prune old archives of job '{}': {:#}",
+                job.name, e
+            );
+            record.prune_error = Some(format!("{:#}", e));
+        }
+    }
+    drop(lock);
+    Ok(())
+}
@@ -0,0 +1,822 @@
+use crate::{
+    backend::{self, Backend},
+    backup,
+    cli::{GlobalArgs, SetupArgs},
+    config::{self, Config, JobSetup},
+    crontab::{remove_cron_job, remove_legacy_cron_job, update_cron_job},
+    files::{remove_if_exists, write_if_changed},
+    job::{self, Credentials, Job, Mode, Overlap, Runner, Scheduler},
+    log_rotation::LogRotation,
+    manifest::Manifest,
+    notify,
+    paths::{JobPaths, Paths},
+    rclone_conf::{KeyChange, RcloneConfig},
+    rclone_crypt,
+    schedule::{self, CronSchedule},
+    secret::Secret,
+    shell, systemd,
+};
+use anyhow::{bail, Context, Result};
+use chrono::Local;
+use clap::ArgMatches;
+use std::{
+    fs::{self, DirBuilder},
+    os::unix::fs::DirBuilderExt,
+    path::Path,
+};
+
+pub fn setup(
+    args: &SetupArgs,
+    matches: &ArgMatches,
+    config: &Config,
+    global: &GlobalArgs,
+) -> Result<()> {
+    let jobs = config::resolve_setup(args, matches, config)?;
+
+    // Validate inputs
+    for job in &jobs {
+        validate_args(job)?;
+        if global.verbose {
+            job.print_origins();
+        }
+        if let Some(count) = args.show_next {
+            print_next_runs(job, count)?;
+        }
+    }
+
+    if !crate::is_rclone_installed()? {
+        println!("Warning: 'rclone' not found in PATH. Please install it before proceeding.");
+    } else if global.verbose {
+        println!("Found 'rclone' in PATH.");
+    }
+
+    let paths = Paths::resolve()?;
+    if global.verbose {
+        paths.print();
+    }
+    for job in &jobs {
+        setup_job(job, &paths, global)?;
+    }
+
+    println!("Remember to keep your access keys secure.");
+    Ok(())
+}
+
+fn setup_job(args: &JobSetup, paths: &Paths, global: &GlobalArgs) -> Result<()> {
+    let job_paths = paths.job(&args.job);
+    let Paths {
+        rclone_config_dir,
+        rclone_config_file,
+        ..
+    } = paths;
+
+    if global.verbose {
+        job_paths.print();
+    }
+
+    for dir in [rclone_config_dir, &job_paths.dir] {
+        if !global.dry_run {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create directory {:?}", dir))?;
+        } else if global.verbose {
+            println!("(dry-run) Would create directory: {}", dir.display());
+        }
+    }
+
+    let password = match args.credentials {
+        Credentials::Encrypted => args
+            .config_password
+            .as_ref()
+            .map(|source| source.read())
+            .transpose()?,
+        _ => None,
+    };
+    let mut rclone_config = RcloneConfig::load(rclone_config_file, password.as_ref())?;
+    let mut manifest = Manifest::load(&job_paths)?.unwrap_or_default();
+    // A remote that was already configured belongs to the user, unless
+    // another job's setup created it.
+    if !rclone_config.has_section(&args.remote) || created_by_other_job(paths, args)? {
+        manifest.config_sections.insert(args.remote.clone());
+    }
+
+    let entries = remote_entries(args, &rclone_config)?;
+    let changes = rclone_config.upsert_section(&args.remote, &entries, backend::REMOTE_KEYS);
+    print_changes(&args.remote, &changes, global);
+
+    let crypt_remote = job::crypt_remote_name(&args.job);
+    let mut recovery = None;
+    if args.encrypt {
+        let (entries, content) = crypt_entries(
+            args,
+            &crypt_remote,
+            &rclone_config,
+            &job_paths.recovery_file,
+        )?;
+        let changes = rclone_config.upsert_section(&crypt_remote, &entries, CRYPT_KEYS);
+        print_changes(&crypt_remote, &changes, global);
+        manifest.config_sections.insert(crypt_remote.clone());
+        recovery = Some(content);
+    } else if rclone_config.remove_section(&crypt_remote) {
+        manifest.config_sections.remove(&crypt_remote);
+        let prefix = if global.dry_run { "(dry-run) " } else { "" };
+        println!(
+            "{}rclone config [{}]: removed, backups are no longer encrypted",
+            prefix, crypt_remote
+        );
+    }
+
+    if global.dry_run {
+        println!(
+            "(dry-run) Would write rclone config file to: {}",
+            rclone_config_file.display()
+        );
+        if global.verbose {
+            println!(
+                "--- rclone.conf content ---\n{}",
+                rclone_config.render_redacted()
+            );
+        }
+    } else {
+        rclone_config.save(rclone_config_file, password.as_ref(), global.verbose)?;
+    }
+    if password.is_some() {
+        warn_unencrypted_jobs(paths, args)?;
+    }
+    if let Some(content) = recovery {
+        write_recovery_file(args, &job_paths.recovery_file, &content, global)?;
+    }
+
+    let mut job = Job {
+        name: args.job.clone(),
+        source: args.source.clone(),
+        remote: args.remote.clone(),
+        bucket: args.bucket.clone(),
+        cron: args.cron.clone(),
+        scheduler: args.scheduler,
+        runner: args.runner,
+        mode: args.mode,
+        retention: args.retention,
+        on_overlap: args.on_overlap,
+        lock_timeout: args.lock_timeout,
+        require_mountpoint: args.require_mountpoint,
+        sentinel: args.sentinel.clone(),
+        max_drop: args.max_drop,
+        max_delete: args.max_delete,
+        logs: args.logs,
+        notify: args.notify.clone(),
+        credentials: args.credentials,
+        config_password: args.config_password.clone(),
+        crypt_remote: args.encrypt.then(|| crypt_remote.clone()),
+        config_fingerprint: None,
+    };
+    job.config_fingerprint = Some(rclone_config.fingerprint(&job.config_sections()));
+
+    if global.dry_run {
+        println!(
+            "(dry-run) Would write job record to: {}",
+            job_paths.record.display()
+        );
+    } else {
+        write_if_changed(
+            &job_paths.record,
+            job.render_record()?.as_bytes(),
+            0o600,
+            global.verbose,
+        )?;
+    }
+
+    match args.runner {
+        Runner::Binary => {
+            if job_paths.backup_script.exists() {
+                println!(
+                    "Switching job '{}' from its backup script to 'rcloneup run'",
+                    args.job
+                );
+            }
+            remove_if_exists(&job_paths.backup_script, global.dry_run, global.verbose)?;
+            manifest.files.remove(&job_paths.backup_script);
+        }
+        Runner::Script => {
+            write_backup_script(&job, &job_paths, global)?;
+            manifest.files.insert(job_paths.backup_script.clone());
+        }
+    }
+    if args.logs.rotation == LogRotation::Logrotate {
+        write_logrotate_config(&job, &job_paths, global)?;
+        manifest.files.insert(job_paths.logrotate_config.clone());
+    } else {
+        remove_if_exists(&job_paths.logrotate_config, global.dry_run, global.verbose)?;
+        manifest.files.remove(&job_paths.logrotate_config);
+    }
+    let command = job.scheduled_command(&job_paths)?;
+
+    // Only one scheduler may trigger a job, so switching removes the other.
+    match args.scheduler {
+        Scheduler::Cron => {
+            if global.dry_run {
+                println!(
+                    "(dry-run) Would update crontab to run '{}' with schedule: '{}'",
+                    command.join(" "),
+                    args.cron
+                );
+            } else {
+                update_cron_job(
+                    &args.job,
+                    &command,
+                    &job_paths.backup_script,
+                    &args.cron,
+                    global.verbose,
+                )?;
+            }
+            systemd::remove_timer(&job_paths, global.dry_run, global.verbose)?;
+            manifest.cron = true;
+            manifest.systemd_timer = false;
+        }
+        Scheduler::Systemd => {
+            let on_calendar = CronSchedule::parse(&args.cron)?.to_on_calendar()?;
+            systemd::install_timer(
+                &args.job,
+                &job_paths,
+                &command,
+                &on_calendar,
+                global.dry_run,
+                global.verbose,
+            )?;
+            remove_cron_job(
+                &args.job,
+                &job_paths.backup_script,
+                global.dry_run,
+                global.verbose,
+            )?;
+            manifest.cron = false;
+            manifest.systemd_timer = true;
+        }
+    }
+
+    manifest.add_state_files(&job_paths);
+    if global.dry_run {
+        println!(
+            "(dry-run) Would write manifest to: {}",
+            job_paths.manifest.display()
+        );
+    } else {
+        manifest.save(&job_paths, global.verbose)?;
+    }
+
+    // The default job takes over from the single script of older releases.
+    if args.job == job::DEFAULT_JOB && paths.legacy_backup_script.exists() {
+        println!(
+            "Replacing legacy backup script {}",
+            paths.legacy_backup_script.display()
+        );
+        remove_legacy_cron_job(&paths.legacy_backup_script, global.dry_run, global.verbose)?;
+        remove_if_exists(&paths.legacy_backup_script, global.dry_run, global.verbose)?;
+    }
+
+    println!("Setup of job '{}' complete!", args.job);
+    if global.dry_run {
+        println!("(dry-run mode - no changes were made)");
+    }
+
+    Ok(())
+}
+
+/// rclone's password options, which it only accepts in obscured form.
+const OBSCURED_KEYS: [&str; 4] = ["pass", "password", "password2", "key_file_pass"];
+
+/// The rclone.conf entries for the job's remote, with password options
+/// obscured.
+fn remote_entries(args: &JobSetup, config: &RcloneConfig) -> Result<Vec<(&'static str, String)>> {
+    let mut entries = args.remote_setup.entries();
+    // The s3 backend reads its secret key verbatim, it has no obscured form.
+    if args.credentials == Credentials::Obscured
+        && entries.iter().any(|(key, _)| *key == "secret_access_key")
+    {
+        bail!(
+            "rclone reads the s3 secret_access_key of remote '{}' verbatim, so it cannot be obscured; use --credentials encrypted instead",
+            args.remote
+        );
+    }
+
+    obscure_passwords(&mut entries, config, &args.remote)?;
+    Ok(entries)
+}
+
+/// Obscures the password options among `entries` of `section`.
+fn obscure_passwords(
+    entries: &mut [(&'static str, String)],
+    config: &RcloneConfig,
+    section: &str,
+) -> Result<()> {
+    for (key, value) in entries.iter_mut() {
+        if !OBSCURED_KEYS.contains(key) {
+            continue;
+        }
+        // Obscuring uses a random IV, so keep the stored value if it still
+        // reveals to the same secret rather than rewriting it on every run.
+        *value = match config.get(section, key) {
+            Some(current) if rclone_crypt::reveal(current).as_deref() == Some(value.as_str()) => {
+                current.to_string()
+            }
+            _ => rclone_crypt::obscure(value)?,
+        };
+    }
+    Ok(())
+}
+
+fn print_changes(section: &str, changes: &[KeyChange], global: &GlobalArgs) {
+    if changes.is_empty() {
+        if global.verbose {
+            println!("Rclone config section [{}] up to date.", section);
+        }
+        return;
+    }
+    let prefix = if global.dry_run { "(dry-run) " } else { "" };
+    for change in changes {
+        println!("{}rclone config [{}]: {}", prefix, section, change);
+    }
+}
+
+/// Every key `crypt_entries` writes.
+const CRYPT_KEYS: &[&str] = &[
+    "type",
+    "remote",
+    "filename_encryption",
+    "directory_name_encryption",
+    "password",
+    "password2",
+];
+
+/// Builds the crypt remote layered over `remote:bucket` together with the
+/// contents of its recovery file. Keys already in rclone.conf or in an
+/// earlier recovery file are reused, since new ones would leave earlier
+/// backups unreadable.
+fn crypt_entries(
+    args: &JobSetup,
+    name: &str,
+    config: &RcloneConfig,
+    recovery_file: &Path,
+) -> Result<(Vec<(&'static str, String)>, String)> {
+    let recovered = if recovery_file.exists() {
+        fs::read_to_string(recovery_file)
+            .with_context(|| format!("Failed to read {}", recovery_file.display()))?
+    } else {
+        String::new()
+    };
+    let mut keys = Vec::new();
+    for (key, what, given) in [
+        ("password", "password", &args.crypt_password),
+        ("password2", "salt", &args.crypt_salt),
+    ] {
+        let prefix = format!("crypt {}: ", what);
+        let current = config
+            .get(name, key)
+            .and_then(rclone_crypt::reveal)
+            .or_else(|| {
+                recovered
+                    .lines()
+                    .find_map(|l| l.strip_prefix(&prefix))
+                    .map(str::to_string)
+            })
+            .map(Secret::new);
+        let value = match (given, current) {
+            (Some(given), Some(current)) if *given != current => bail!(
+                "Job '{}' already encrypts with a different crypt {}; changing it would make existing backups unreadable",
+                args.job,
+                what
+            ),
+            (Some(given), _) => given.clone(),
+            (None, Some(current)) => current,
+            (None, None) => Secret::new(rclone_crypt::generate_password()?),
+        };
+        keys.push(value);
+    }
+
+    let mut entries = vec![
+        ("type", "crypt".to_string()),
+        ("remote", format!("{}:{}", args.remote, args.bucket)),
+        ("filename_encryption", "standard".to_string()),
+        ("directory_name_encryption", "true".to_string()),
+        ("password", keys[0].expose().to_string()),
+        ("password2", keys[1].expose().to_string()),
+    ];
+    obscure_passwords(&mut entries, config, name)?;
+
+    let mut section = RcloneConfig::default();
+    section.upsert_section(name, &entries, &[]);
+    let content = format!(
+        r#"# Recovery material for the encrypted backups of rcloneup job '{job}'.
+#
+# WARNING: the password and salt below are the only way to decrypt these
+# backups. If they are lost, nobody can recover the data. Keep a copy of
+# this file somewhere safe that is not on this machine.
+
+crypt password: {password}
+crypt salt: {salt}
+
+# rclone remote to restore with (password and salt in obscured form):
+{section}"#,
+        job = args.job,
+        password = keys[0].expose(),
+        salt = keys[1].expose(),
+        section = section.render()
+    );
+    Ok((entries, content))
+}
+
+fn write_recovery_file(
+    args: &JobSetup,
+    path: &Path,
+    content: &str,
+    global: &GlobalArgs,
+) -> Result<()> {
+    if global.dry_run {
+        println!(
+            "(dry-run) Would write crypt recovery file to: {}",
+            path.display()
+        );
+        return Ok(());
+    }
+    if let Some(dir) = path.parent() {
+        DirBuilder::new()
+            .recursive(true)
+            .mode(0o700)
+            .create(dir)
+            .with_context(|| format!("Failed to create directory {:?}", dir))?;
+    }
+    if write_if_changed(path, content.as_bytes(), 0o600, global.verbose)? {
+        println!(
+            "WARNING: backups of job '{}' are encrypted with the password and salt in {}. Without them the backups cannot be decrypted; copy the file somewhere safe off this machine.",
+            args.job,
+            path.display()
+        );
+    }
+    Ok(())
+}
+
+/// Whether the manifest of a job other than `args.job` lists its remote.
+fn created_by_other_job(paths: &Paths, args: &JobSetup) -> Result<bool> {
+    for job in Job::list(paths)? {
+        if job.name == args.job {
+            continue;
+        }
+        let manifest = Manifest::load(&paths.job(&job.name))?.unwrap_or_default();
+        if manifest.config_sections.contains(&args.remote) {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Other jobs have to load the password too once rclone.conf is encrypted.
+fn warn_unencrypted_jobs(paths: &Paths, args: &JobSetup) -> Result<()> {
+    for other in Job::list(paths)? {
+        if other.name != args.job && other.config_password.is_none() {
+            println!(
+                "Warning: rclone.conf is now encrypted; re-run setup for job '{}' with --credentials encrypted so it can still read it.",
+                other.name
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Writes the bash script that legacy `--runner script` jobs schedule.
+fn write_backup_script(job: &Job, job_paths: &JobPaths, global: &GlobalArgs) -> Result<()> {
+    let script_content = backup::script_content(job, job_paths);
+
+    if global.dry_run {
+        println!(
+            "(dry-run) Would write backup script to: {}",
+            job_paths.backup_script.display()
+        );
+        if global.verbose {
+            println!("--- backup script content ---\n{}", script_content);
+        }
+        return Ok(());
+    }
+    write_if_changed(
+        &job_paths.backup_script,
+        script_content.as_bytes(),
+        0o755,
+        global.verbose,
+    )?;
+    Ok(())
+}
+
+/// Writes the job's logrotate config and says how to install it; rcloneup
+/// cannot put it into /etc/logrotate.d without root.
+fn write_logrotate_config(job: &Job, job_paths: &JobPaths, global: &GlobalArgs) -> Result<()> {
+    let content = job.logs.logrotate_config(&job_paths.log_file);
+    if global.dry_run {
+        println!(
+            "(dry-run) Would write logrotate config to: {}",
+            job_paths.logrotate_config.display()
+        );
+        if global.verbose {
+            println!("--- logrotate config ---\n{}", content);
+        }
+        return Ok(());
+    }
+    if write_if_changed(
+        &job_paths.logrotate_config,
+        content.as_bytes(),
+        0o644,
+        global.verbose,
+    )? {
+        println!(
+            "Wrote logrotate config for job '{}'. Install it with:\n  sudo cp {} /etc/logrotate.d/rcloneup-{}",
+            job.name,
+            shell::quote(&job_paths.logrotate_config.to_string_lossy()),
+            job.name
+        );
+    }
+    Ok(())
+}
+
+fn print_next_runs(job: &JobSetup, count: usize) -> Result<()> {
+    let runs = CronSchedule::parse(&job.cron)?.next_runs(Local::now(), count);
+    println!("Next {} runs of job '{}' ({}):", count, job.job, job.cron);
+    for run in &runs {
+        println!("  {}", schedule::format_run(run));
+    }
+    Ok(())
+}
+
+fn validate_args(args: &JobSetup) -> Result<()> {
+    job::validate_name(&args.job)?;
+    for (what, value) in [
+        ("Backup source", args.source.as_str()),
+        ("Remote name", args.remote.as_str()),
+        ("Bucket", args.bucket.as_str()),
+        ("Cron schedule", args.cron.as_str()),
+    ] {
+        shell::reject_control_chars(what, value)?;
+    }
+    if args.remote.is_empty() || args.remote.contains(':') || args.remote.starts_with('-') {
+        bail!(
+            "Remote name must be non-empty, must not contain ':' and must not start with '-', got '{}'",
+            args.remote
+        );
+    }
+    args.remote_setup.validate()?;
+    if args.runner == Runner::Script && args.on_overlap == Overlap::Kill {
+        bail!("--on-overlap kill needs --runner binary");
+    }
+    if args.mode != Mode::Versioned && !args.retention.is_empty() {
+        bail!("--keep-last, --keep-daily, --keep-weekly, --keep-monthly and --max-age need --mode versioned");
+    }
+    for (flag, count) in [
+        ("--keep-last", args.retention.keep_last),
+        ("--keep-daily", args.retention.keep_daily),
+        ("--keep-weekly", args.retention.keep_weekly),
+        ("--keep-monthly", args.retention.keep_monthly),
+    ] {
+        if count == Some(0) {
+            bail!(
+                "{} must be at least 1; leave it out to not keep archives by that rule",
+                flag
+            );
+        }
+    }
+    if args.runner == Runner::Script && args.mode == Mode::Versioned {
+        bail!("--mode versioned needs --runner binary");
+    }
+    if args.runner == Runner::Script
+        && (args.require_mountpoint || args.sentinel.is_some() || args.max_drop.is_some())
+    {
+        bail!("--require-mountpoint, --sentinel and --max-drop need --runner binary");
+    }
+    if let Some(sentinel) = &args.sentinel {
+        shell::reject_control_chars("Sentinel", sentinel)?;
+        let path = Path::new(sentinel);
+        if sentinel.is_empty()
+            || path.is_absolute()
+            || path.components().any(|c| c.as_os_str() == "..")
+        {
+            bail!(
+                "Sentinel must be a relative path inside the backup source, got '{}'",
+                sentinel
+            );
+        }
+    }
+    if args.runner == Runner::Script && args.logs.rotation == LogRotation::Rotate {
+        bail!("--log-rotation rotate needs --runner binary; use logrotate or off with scripts");
+    }
+    if let Some(notify) = &args.notify {
+        if args.runner == Runner::Script {
+            bail!("--notify-url needs --runner binary");
+        }
+        // The URL often carries a token, so errors never show it.
+        shell::reject_secret_control_chars("Notification URL", &notify.url)?;
+        let url = notify.url.expose();
+        if !url.starts_with("http://") && !url.starts_with("https://") {
+            bail!("--notify-url must be an http:// or https:// URL");
+        }
+        if let Some(template) = &notify.template {
+            shell::reject_control_chars("Notification template", template)?;
+        }
+        notify::validate(notify, &notify::sample_record(&args.job, false))?;
+    }
+    if args.logs.keep == 0 {
+        bail!("--log-keep must be at least 1");
+    }
+    if args.max_drop.is_some_and(|p| p > 100) {
+        bail!("--max-drop is a percentage and must be at most 100");
+    }
+    for (what, value) in [
+        ("Crypt password", &args.crypt_password),
+        ("Crypt salt", &args.crypt_salt),
+    ] {
+        let Some(value) = value else { continue };
+        if !args.encrypt {
+            bail!("{} only applies with --encrypt", what);
+        }
+        shell::reject_secret_control_chars(what, value)?;
+        if value.expose().is_empty() {
+            bail!("{} must not be empty", what);
+        }
+    }
+    if args.remote_setup.backend() == Backend::Local && !Path::new(&args.bucket).is_absolute() {
+        bail!(
+            "Local remotes need an absolute --bucket path, got '{}'",
+            args.bucket
+        );
+    }
+    match (args.credentials, &args.config_password) {
+        (Credentials::Encrypted, None) => bail!(
+            "--credentials encrypted needs --config-password-file or --config-password-command"
+        ),
+        (Credentials::Encrypted, Some(_)) | (_, None) => {}
+        (_, Some(_)) => bail!(
+            "--config-password-file and --config-password-command only apply to --credentials encrypted"
+        ),
+    }
+    if !Path::new(&args.source).exists() {
+        bail!("Backup source directory does not exist: {}", args.source);
+    }
+    let schedule = CronSchedule::parse(&args.cron)?;
+    if schedule.next_runs(Local::now(), 1).is_empty() {
+        bail!("Cron schedule '{}' never fires", args.cron);
+    }
+    if args.scheduler == Scheduler::Systemd {
+        schedule.to_on_calendar()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    fn validate(args: &[&str]) -> Result<()> {
+        let base = ["--source", "/", "--access-key", "a", "--secret-key", "b"];
+        let args: Vec<&str> = base.iter().chain(args).copied().collect();
+        for job in testing::setup_jobs(&args, "")? {
+            validate_args(&job)?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn retention_and_archives_need_versioned_mode_and_the_binary() {
+        validate(&["--mode", "copy"]).unwrap();
+        validate(&["--mode", "versioned", "--max-age", "30"]).unwrap();
+        for mode in ["sync", "copy"] {
+            let err = validate(&["--mode", mode, "--keep-last", "3"]).unwrap_err();
+            assert!(
+                err.to_string().ends_with("need --mode versioned"),
+                "{}",
+                err
+            );
+        }
+        let err = validate(&["--mode", "versioned", "--runner", "script"]).unwrap_err();
+        assert_eq!(err.to_string(), "--mode versioned needs --runner binary");
+    }
+
+    #[test]
+    fn keep_rules_must_keep_something() {
+        validate(&["--mode", "versioned", "--keep-last", "1"]).unwrap();
+        for flag in [
+            "--keep-last",
+            "--keep-daily",
+            "--keep-weekly",
+            "--keep-monthly",
+        ] {
+            let err = validate(&["--mode", "versioned", flag, "0"]).unwrap_err();
+            assert!(
+                err.to_string()
+                    .starts_with(&format!("{} must be at least 1", flag)),
+                "{}",
+                err
+            );
+        }
+    }
+
+    fn job_setup(args: &[&str]) -> JobSetup {
+        let base = ["--source", "/", "--bucket", "/backups"];
+        let args: Vec<&str> = base.iter().chain(args).copied().collect();
+        testing::setup_jobs(&args, "").unwrap().remove(0)
+    }
+
+    #[test]
+    fn remote_passwords_are_obscured_and_kept_while_unchanged() {
+        let args = job_setup(&[
+            "--backend",
+            "webdav",
+            "--url",
+            "https://cloud.lan/dav",
+            "--password",
+            "hunter2",
+        ]);
+        let entries = remote_entries(&args, &RcloneConfig::default()).unwrap();
+        let (_, pass) = entries.iter().find(|(k, _)| *k == "pass").unwrap();
+        assert_ne!(pass, "hunter2");
+        assert_eq!(rclone_crypt::reveal(pass).as_deref(), Some("hunter2"));
+
+        let mut config = RcloneConfig::default();
+        config.upsert_section(&args.remote, &entries, backend::REMOTE_KEYS);
+        let again = remote_entries(&args, &config).unwrap();
+        assert_eq!(again, entries);
+    }
+
+    /// The crypt password and salt in a recovery file.
+    fn recovered_keys(content: &str) -> (String, String) {
+        let find = |prefix: &str| {
+            content
+                .lines()
+                .find_map(|l| l.strip_prefix(prefix))
+                .unwrap()
+                .to_string()
+        };
+        (find("crypt password: "), find("crypt salt: "))
+    }
+
+    #[test]
+    fn crypt_remote_wraps_the_bucket_with_new_keys() {
+        let dir = testing::TempDir::new();
+        let args = job_setup(&["--backend", "local", "--encrypt"]);
+        let name = job::crypt_remote_name(&args.job);
+        let (entries, content) = crypt_entries(
+            &args,
+            &name,
+            &RcloneConfig::default(),
+            &dir.path().join("recovery"),
+        )
+        .unwrap();
+        let get = |key| {
+            entries
+                .iter()
+                .find(|(k, _)| *k == key)
+                .map(|(_, v)| v.as_str())
+                .unwrap()
+        };
+        assert_eq!(get("type"), "crypt");
+        assert_eq!(get("remote"), "local:/backups");
+        let (password, salt) = recovered_keys(&content);
+        assert_ne!(password, salt);
+        assert_eq!(rclone_crypt::reveal(get("password")), Some(password));
+        assert_eq!(rclone_crypt::reveal(get("password2")), Some(salt));
+        assert!(content.contains(&format!("[{}]\ntype = crypt\n", name)));
+    }
+
+    #[test]
+    fn crypt_keys_are_reused_from_the_config_or_the_recovery_file() {
+        let dir = testing::TempDir::new();
+        let recovery = dir.path().join("recovery");
+        let args = job_setup(&["--backend", "local", "--encrypt"]);
+        let name = job::crypt_remote_name(&args.job);
+        let (entries, content) =
+            crypt_entries(&args, &name, &RcloneConfig::default(), &recovery).unwrap();
+
+        let mut config = RcloneConfig::default();
+        config.upsert_section(&name, &entries, CRYPT_KEYS);
+        let (again, _) = crypt_entries(&args, &name, &config, &recovery).unwrap();
+        assert_eq!(again, entries);
+
+        // With the section gone, the recovery file still has the keys.
+        fs::write(&recovery, &content).unwrap();
+        let (_, restored) =
+            crypt_entries(&args, &name, &RcloneConfig::default(), &recovery).unwrap();
+        assert_eq!(recovered_keys(&restored), recovered_keys(&content));
+    }
+
+    #[test]
+    fn crypt_keys_cannot_change_under_existing_backups() {
+        let dir = testing::TempDir::new();
+        let recovery = dir.path().join("recovery");
+        let given = job_setup(&["--backend", "local", "--encrypt", "--crypt-password", "pw1"]);
+        let name = job::crypt_remote_name(&given.job);
+        let (_, content) =
+            crypt_entries(&given, &name, &RcloneConfig::default(), &recovery).unwrap();
+        assert_eq!(recovered_keys(&content).0, "pw1");
+        fs::write(&recovery, &content).unwrap();
+
+        let changed = job_setup(&["--backend", "local", "--encrypt", "--crypt-password", "pw2"]);
+        let err = crypt_entries(&changed, &name, &RcloneConfig::default(), &recovery).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("already encrypts with a different crypt password"));
+    }
+}
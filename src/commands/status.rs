@@ -0,0 +1,352 @@
+use crate::{
+    backup,
+    cli::{GlobalArgs, StatusArgs},
+    crontab::{cron_line, find_cron_job},
+    job::{self, Job, Runner, Scheduler},
+    paths::{JobPaths, Paths},
+    rclone_conf::RcloneConfig,
+    schedule::{self, CronSchedule},
+    state::{self, Outcome, RunRecord},
+    systemd,
+};
+use anyhow::Result;
+use chrono::{DateTime, Local, Utc};
+use serde::Serialize;
+use std::{fmt, fs};
+
+/// Whether something setup installed is still what it wrote.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Check {
+    Ok,
+    /// Present, but different from what setup would write now.
+    Changed,
+    Missing,
+    /// Could not be compared, e.g. because rclone.conf could not be decrypted.
+    Unknown,
+}
+
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Check::Ok => write!(f, "ok"),
+            Check::Changed => write!(f, "changed since setup"),
+            Check::Missing => write!(f, "missing"),
+            Check::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct Checks {
+    rclone_config: Check,
+    schedule: Check,
+    /// Only for jobs run through the legacy backup script.
+    script: Option<Check>,
+}
+
+/// Everything `status` reports about one job, also its `--json` form.
+#[derive(Debug, Serialize)]
+struct JobStatus {
+    name: String,
+    source: String,
+    destination: String,
+    mode: String,
+    scheduler: String,
+    schedule: String,
+    next_run: Option<DateTime<Local>>,
+    last_run: Option<RunRecord>,
+    last_run_seconds: Option<f64>,
+    last_success: Option<DateTime<Utc>>,
+    last_success_age_seconds: Option<i64>,
+    checks: Checks,
+}
+
+#[derive(Debug, Serialize)]
+struct Status {
+    rclone_installed: bool,
+    jobs: Vec<JobStatus>,
+}
+
+pub fn status(args: &StatusArgs, global: &GlobalArgs) -> Result<()> {
+    let paths = Paths::resolve()?;
+    if global.verbose && !args.json {
+        paths.print();
+    }
+
+    let jobs = super::select_jobs(&paths, args.job.as_deref())?;
+    // Without the rclone.conf password the rest of the status is still useful.
+    let config = match job::config_password(&jobs)
+        .and_then(|password| RcloneConfig::load(&paths.rclone_config_file, password.as_ref()))
+    {
+        Ok(config) => Some(config),
+        Err(e) => {
+            eprintln!("Warning: cannot check the rclone config: {:#}", e);
+            None
+        }
+    };
+    let status = Status {
+        rclone_installed: crate::is_rclone_installed()?,
+        jobs: jobs
+            .iter()
+            .map(|job| job_status(&paths.job(&job.name), config.as_ref(), job))
+            .collect::<Result<_>>()?,
+    };
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&status)?);
+        return Ok(());
+    }
+
+    if status.rclone_installed {
+        println!("rclone: installed");
+    } else {
+        println!("rclone: not found in PATH");
+    }
+    if jobs.is_empty() {
+        println!("No backup jobs configured.");
+    }
+    for (job, job_status) in jobs.iter().zip(&status.jobs) {
+        print_job(&paths.job(&job.name), job, job_status);
+    }
+    Ok(())
+}
+
+fn job_status(job_paths: &JobPaths, config: Option<&RcloneConfig>, job: &Job) -> Result<JobStatus> {
+    let runs = state::load(job_paths)?;
+    let last_run = runs.last().cloned();
+    let last_success = runs
+        .iter()
+        .rev()
+        .find(|run| run.outcome == Outcome::Success)
+        .map(|run| run.finished);
+    let next_run = CronSchedule::parse(&job.cron)
+        .ok()
+        .and_then(|schedule| schedule.next_runs(Local::now(), 1).pop());
+
+    Ok(JobStatus {
+        name: job.name.clone(),
+        source: job.source.clone(),
+        destination: job.destination(),
+        mode: job.mode.to_string(),
+        scheduler: job.scheduler.to_string(),
+        schedule: job.cron.clone(),
+        next_run,
+        last_run_seconds: last_run
+            .as_ref()
+            .map(|run| (run.finished - run.started).as_seconds_f64()),
+        last_run,
+        last_success,
+        last_success_age_seconds: last_success.map(|time| (Utc::now() - time).num_seconds()),
+        checks: Checks {
+            rclone_config: check_config(config, job),
+            schedule: check_schedule(job_paths, job)?,
+            script: (job.runner == Runner::Script).then(|| check_script(job_paths, job)),
+        },
+    })
+}
+
+fn check_config(config: Option<&RcloneConfig>, job: &Job) -> Check {
+    let Some(config) = config else {
+        return Check::Unknown;
+    };
+    let sections = job.config_sections();
+    if !sections.iter().all(|section| config.has_section(section)) {
+        return Check::Missing;
+    }
+    match &job.config_fingerprint {
+        Some(fingerprint) if *fingerprint == config.fingerprint(&sections) => Check::Ok,
+        Some(_) => Check::Changed,
+        None => Check::Unknown,
+    }
+}
+
+fn check_schedule(job_paths: &JobPaths, job: &Job) -> Result<Check> {
+    let command = job.scheduled_command(job_paths)?;
+    Ok(match job.scheduler {
+        Scheduler::Cron => match find_cron_job(&job.name)? {
+            None => Check::Missing,
+            Some(line) if line == cron_line(&command, &job.cron) => Check::Ok,
+            Some(_) => Check::Changed,
+        },
+        Scheduler::Systemd => {
+            let on_calendar = CronSchedule::parse(&job.cron).and_then(|s| s.to_on_calendar());
+            if !job_paths.timer_unit.exists() || !job_paths.service_unit.exists() {
+                Check::Missing
+            } else if on_calendar.is_ok_and(|on_calendar| {
+                systemd::units_match(&job.name, job_paths, &command, &on_calendar)
+            }) {
+                Check::Ok
+            } else {
+                Check::Changed
+            }
+        }
+    })
+}
+
+fn check_script(job_paths: &JobPaths, job: &Job) -> Check {
+    match fs::read_to_string(&job_paths.backup_script) {
+        Err(_) => Check::Missing,
+        Ok(content) if content == backup::script_content(job, job_paths) => Check::Ok,
+        Ok(_) => Check::Changed,
+    }
+}
+
+fn print_job(job_paths: &JobPaths, job: &Job, status: &JobStatus) {
+    println!();
+    println!(
+        "Job '{}': {} -> {}",
+        job.name, job.source, status.destination
+    );
+
+    let next = match &status.next_run {
+        Some(time) => format!(", next run {}", schedule::format_run(time)),
+        None => String::new(),
+    };
+    println!("  Schedule: {} '{}'{}", job.scheduler, job.cron, next);
+
+    match &status.last_run {
+        Some(run) => {
+            let mut line = format!(
+                "  Last run: {} at {}, took {:.1}s",
+                run.outcome,
+                run.started.with_timezone(&Local).format("%Y-%m-%d %H:%M"),
+                status.last_run_seconds.unwrap_or_default()
+            );
+            if run.outcome == Outcome::Success || run.outcome == Outcome::Failed {
+                let stats = &run.stats;
+                line.push_str(&format!(
+                    ", {} in {} file(s) transferred, {} deleted",
+                    backup::format_bytes(stats.bytes),
+                    stats.transfers,
+                    stats.deletes
+                ));
+                if stats.errors > 0 {
+                    line.push_str(&format!(", {} error(s)", stats.errors));
+                }
+                if stats.retries > 0 {
+                    line.push_str(&format!(", retried {} time(s)", stats.retries));
+                }
+            }
+            println!("{}", line);
+            if let Some(error) = &run.error {
+                println!("    {}", error);
+            }
+            for error in &run.rclone_errors {
+                println!("    rclone: {}", error);
+            }
+            if let Some(error) = &run.prune_error {
+                println!("    Pruning failed: {}", error);
+            }
+        }
+        None => println!("  Last run: never"),
+    }
+    match status.last_success_age_seconds {
+        Some(age) => println!("  Last successful backup: {} ago", format_age(age)),
+        None => println!("  Last successful backup: never"),
+    }
+
+    println!(
+        "  rclone config [{}]: {}",
+        job.config_sections().join("], ["),
+        status.checks.rclone_config
+    );
+    match job.scheduler {
+        Scheduler::Cron => println!("  Cron job: {}", status.checks.schedule),
+        Scheduler::Systemd => {
+            let state = systemd::timer_state(job_paths)
+                .map(|state| format!(" ({})", state))
+                .unwrap_or_default();
+            println!(
+                "  Systemd timer {}: {}{}",
+                job_paths.timer_name(),
+                status.checks.schedule,
+                state
+            );
+        }
+    }
+    match status.checks.script {
+        Some(check) => println!(
+            "  Backup script {}: {}",
+            job_paths.backup_script.display(),
+            check
+        ),
+        None => println!("  Runs: rcloneup run --job {}", job.name),
+    }
+
+    if job.crypt_remote.is_some() {
+        if job_paths.recovery_file.exists() {
+            println!(
+                "  Crypt recovery file: {}",
+                job_paths.recovery_file.display()
+            );
+        } else {
+            println!("  Crypt recovery file: missing");
+        }
+    }
+
+    println!("  Mode: {}", job.mode);
+    println!("  Log: {}", job.logs.describe());
+    if !job.retention.is_empty() {
+        println!("  Retention: {}", job.retention.describe());
+    }
+
+    let mut guards = Vec::new();
+    if job.require_mountpoint {
+        guards.push("mountpoint".to_string());
+    }
+    if let Some(sentinel) = &job.sentinel {
+        guards.push(format!("sentinel {}", sentinel));
+    }
+    if let Some(max_drop) = job.max_drop {
+        guards.push(format!("max drop {}%", max_drop));
+    }
+    if let Some(max_delete) = job.max_delete {
+        guards.push(format!("max delete {}", max_delete));
+    }
+    if !guards.is_empty() {
+        println!("  Guards: {}", guards.join(", "));
+    }
+    if let Some(notify) = &job.notify {
+        println!("  Notify: {}", notify.describe());
+    }
+}
+
+/// Formats an age in seconds as e.g. `2d 4h`, `3h 12m` or `5m`.
+fn format_age(seconds: i64) -> String {
+    let minutes = seconds.max(0) / 60;
+    let (days, hours, minutes) = (minutes / 1440, minutes / 60 % 24, minutes % 60);
+    if days > 0 {
+        format!("{}d {}h", days, hours)
+    } else if hours > 0 {
+        format!("{}h {}m", hours, minutes)
+    } else if minutes > 0 {
+        format!("{}m", minutes)
+    } else {
+        "less than a minute".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    #[test]
+    fn config_check_compares_the_recorded_fingerprint() {
+        let config = RcloneConfig::parse("[minio]\ntype = s3\nprovider = Minio\n");
+        let mut job = testing::job("default", "/data");
+        job.config_fingerprint = Some(config.fingerprint(&job.config_sections()));
+        assert_eq!(check_config(Some(&config), &job), Check::Ok);
+
+        let edited = RcloneConfig::parse("[minio]\ntype = s3\nprovider = Other\n");
+        assert_eq!(check_config(Some(&edited), &job), Check::Changed);
+
+        let other = RcloneConfig::parse("[gdrive]\ntype = drive\n");
+        assert_eq!(check_config(Some(&other), &job), Check::Missing);
+
+        assert_eq!(check_config(None, &job), Check::Unknown);
+        job.config_fingerprint = None;
+        assert_eq!(check_config(Some(&config), &job), Check::Unknown);
+    }
+}
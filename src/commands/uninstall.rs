@@ -0,0 +1,148 @@
+use crate::{
+    cli::{GlobalArgs, UninstallArgs},
+    crontab::{remove_cron_job, remove_legacy_cron_job},
+    files::remove_if_exists,
+    job::{self, Job},
+    log_rotation,
+    manifest::Manifest,
+    paths::Paths,
+    rclone_conf::RcloneConfig,
+    systemd,
+};
+use anyhow::Result;
+use std::fs;
+
+pub fn uninstall(args: &UninstallArgs, global: &GlobalArgs) -> Result<()> {
+    let paths = Paths::resolve()?;
+    if global.verbose {
+        paths.print();
+    }
+
+    let jobs = super::select_jobs(&paths, args.job.as_deref())?;
+    // A remote section is only ours to remove once no remaining job uses it.
+    let remaining: Vec<Job> = Job::list(&paths)?
+        .into_iter()
+        .filter(|j| !jobs.iter().any(|removed| removed.name == j.name))
+        .collect();
+
+    // Read everything that can fail before removing anything, so that a
+    // wrong config password does not leave jobs half uninstalled.
+    let mut manifests = Vec::new();
+    for job in &jobs {
+        let job_paths = paths.job(&job.name);
+        manifests.push(match Manifest::load(&job_paths)? {
+            Some(manifest) => manifest,
+            None => {
+                // Setup stopped before it got to save the manifest. The
+                // schedule is marked with the job's name, so it is ours.
+                println!(
+                    "No manifest for job '{}', removing only its schedule, record and state files",
+                    job.name
+                );
+                let mut manifest = Manifest {
+                    cron: true,
+                    systemd_timer: true,
+                    ..Manifest::default()
+                };
+                manifest.add_state_files(&job_paths);
+                manifest
+            }
+        });
+    }
+    let mut config = if args.keep_config {
+        None
+    } else {
+        // Any job that shared the config knows its password, removed or not.
+        let all: Vec<Job> = jobs.iter().chain(&remaining).cloned().collect();
+        let password = job::config_password(&all)?;
+        let config = RcloneConfig::load(&paths.rclone_config_file, password.as_ref())?;
+        let encrypted = paths.rclone_config_file.exists()
+            && RcloneConfig::is_encrypted_file(&paths.rclone_config_file)?;
+        Some((config, password.filter(|_| encrypted)))
+    };
+
+    for (job, manifest) in jobs.iter().zip(&manifests) {
+        let job_paths = paths.job(&job.name);
+        println!("Uninstalling job '{}'", job.name);
+        if manifest.cron {
+            remove_cron_job(
+                &job.name,
+                &job_paths.backup_script,
+                global.dry_run,
+                global.verbose,
+            )?;
+        }
+        if manifest.systemd_timer {
+            systemd::remove_timer(&job_paths, global.dry_run, global.verbose)?;
+        }
+        for file in &manifest.files {
+            remove_if_exists(file, global.dry_run, global.verbose)?;
+        }
+        // Runs create these, up to the job's `log_keep`, after setup.
+        for file in log_rotation::rotated_logs(&job_paths) {
+            remove_if_exists(&file, global.dry_run, global.verbose)?;
+        }
+        remove_if_exists(&job_paths.manifest, global.dry_run, global.verbose)?;
+        if !global.dry_run && job_paths.dir.exists() {
+            // Leave the directory behind if the user put anything else in it.
+            let _ = fs::remove_dir(&job_paths.dir);
+        }
+    }
+
+    if args.job.is_none() {
+        remove_legacy_cron_job(&paths.legacy_backup_script, global.dry_run, global.verbose)?;
+        remove_if_exists(&paths.legacy_backup_script, global.dry_run, global.verbose)?;
+    }
+
+    let mut config_changed = false;
+    for (job, manifest) in jobs.iter().zip(&manifests) {
+        let recovery_file = paths.job(&job.name).recovery_file;
+        if recovery_file.exists() {
+            println!(
+                "Keeping crypt recovery file {}; delete it yourself once the encrypted backups are no longer needed",
+                recovery_file.display()
+            );
+        }
+        if !manifest.config_sections.contains(&job.remote) && global.verbose {
+            println!(
+                "Keeping rclone config [{}], it existed before setup",
+                job.remote
+            );
+        }
+        for section in &manifest.config_sections {
+            let Some((config, _)) = &mut config else {
+                println!("Keeping rclone config [{}] (--keep-config)", section);
+                continue;
+            };
+            if remaining
+                .iter()
+                .any(|j| &j.remote == section || j.crypt_remote.as_ref() == Some(section))
+            {
+                if global.verbose {
+                    println!(
+                        "Keeping rclone config [{}], still used by another job",
+                        section
+                    );
+                }
+            } else if config.remove_section(section) {
+                config_changed = true;
+                if global.dry_run {
+                    println!("(dry-run) Would remove rclone config [{}]", section);
+                } else {
+                    println!("Removed rclone config [{}]", section);
+                }
+            } else if global.verbose {
+                println!("rclone config [{}]: already absent", section);
+            }
+        }
+    }
+    if let Some((config, password)) = config.filter(|_| config_changed && !global.dry_run) {
+        config.save(&paths.rclone_config_file, password.as_ref(), global.verbose)?;
+    }
+
+    println!("Uninstall complete!");
+    if global.dry_run {
+        println!("(dry-run mode - no changes were made)");
+    }
+    Ok(())
+}
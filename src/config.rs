@@ -0,0 +1,782 @@
+use crate::{
+    backend::{Backend, RemoteSetup},
+    cli::{GlobalArgs, SetupArgs},
+    job::{
+        Credentials, Mode, Overlap, PasswordSource, Runner, Scheduler, DEFAULT_JOB,
+        DEFAULT_LOCK_TIMEOUT,
+    },
+    log_rotation::{LogPolicy, LogRotation, LogSize, DEFAULT_LOG_KEEP, DEFAULT_LOG_MAX_SIZE},
+    notify::{Notify, NotifyOn, Preset},
+    provider::Provider,
+    retention::Retention,
+    secret::Secret,
+};
+use anyhow::{bail, Context, Result};
+use clap::{parser::ValueSource, ArgMatches};
+use serde::Deserialize;
+use std::{
+    collections::BTreeMap,
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+const CONFIG_FILE_NAME: &str = "rcloneup.toml";
+
+/// Contents of rcloneup.toml.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigFile {
+    #[serde(default)]
+    pub options: Options,
+    #[serde(default)]
+    pub remotes: BTreeMap<String, RemoteConfig>,
+    #[serde(default)]
+    pub jobs: BTreeMap<String, JobConfig>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Options {
+    #[serde(default)]
+    pub verbose: bool,
+    #[serde(default)]
+    pub dry_run: bool,
+    pub credentials: Option<Credentials>,
+    pub config_password_file: Option<String>,
+    pub config_password_command: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RemoteConfig {
+    pub backend: Option<Backend>,
+    pub provider: Option<Provider>,
+    pub endpoint: Option<String>,
+    pub region: Option<String>,
+    pub account_id: Option<String>,
+    pub location_constraint: Option<String>,
+    pub access_key: Option<Secret>,
+    pub secret_key: Option<Secret>,
+    pub host: Option<String>,
+    pub port: Option<u16>,
+    pub key_file: Option<String>,
+    pub url: Option<String>,
+    pub webdav_vendor: Option<String>,
+    pub user: Option<String>,
+    pub password: Option<Secret>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct JobConfig {
+    pub source: Option<String>,
+    pub remote: Option<String>,
+    pub bucket: Option<String>,
+    pub cron: Option<String>,
+    pub scheduler: Option<Scheduler>,
+    pub runner: Option<Runner>,
+    pub mode: Option<Mode>,
+    pub keep_last: Option<u32>,
+    pub keep_daily: Option<u32>,
+    pub keep_weekly: Option<u32>,
+    pub keep_monthly: Option<u32>,
+    pub max_age: Option<u32>,
+    pub on_overlap: Option<Overlap>,
+    pub lock_timeout: Option<u64>,
+    pub require_mountpoint: Option<bool>,
+    pub sentinel: Option<String>,
+    pub max_drop: Option<u32>,
+    pub max_delete: Option<u64>,
+    pub log_rotation: Option<LogRotation>,
+    pub log_max_size: Option<LogSize>,
+    pub log_max_age: Option<u32>,
+    pub log_keep: Option<u32>,
+    pub notify_url: Option<Secret>,
+    pub notify_preset: Option<Preset>,
+    pub notify_on: Option<NotifyOn>,
+    pub notify_template: Option<String>,
+    pub encrypt: Option<bool>,
+    pub crypt_password: Option<Secret>,
+    pub crypt_salt: Option<Secret>,
+}
+
+/// The parsed config file, or an empty one if none was found.
+#[derive(Debug, Default)]
+pub struct Config {
+    pub file: ConfigFile,
+}
+
+impl Config {
+    /// Loads `--config` if given, otherwise the first rcloneup.toml found in
+    /// `$XDG_CONFIG_HOME/rcloneup/` and then `/etc/rcloneup/`.
+    pub fn load(global: &GlobalArgs) -> Result<Self> {
+        let path = match &global.config {
+            Some(path) => {
+                if !path.exists() {
+                    bail!("Config file does not exist: {}", path.display());
+                }
+                Some(path.clone())
+            }
+            None => search_paths().into_iter().find(|p| p.exists()),
+        };
+
+        let Some(path) = path else {
+            if global.verbose {
+                println!("No rcloneup.toml found, using flags and environment only.");
+            }
+            return Ok(Self::default());
+        };
+
+        if global.verbose {
+            println!("Using config file: {}", path.display());
+        }
+        let text = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read config file {}", path.display()))?;
+        let file = toml::from_str(&text)
+            .with_context(|| format!("Invalid config file {}", path.display()))?;
+        Ok(Self { file })
+    }
+}
+
+fn search_paths() -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    if let Some(dir) = dirs::config_dir() {
+        paths.push(dir.join("rcloneup").join(CONFIG_FILE_NAME));
+    }
+    paths.push(Path::new("/etc/rcloneup").join(CONFIG_FILE_NAME));
+    paths
+}
+
+/// Where a resolved setting came from, lowest precedence first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Origin {
+    Default,
+    ConfigFile,
+    Environment,
+    CommandLine,
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Origin::Default => write!(f, "default"),
+            Origin::ConfigFile => write!(f, "config file"),
+            Origin::Environment => write!(f, "environment"),
+            Origin::CommandLine => write!(f, "command line"),
+        }
+    }
+}
+
+/// A job's setup values after layering defaults, rcloneup.toml,
+/// environment variables and command line flags.
+#[derive(Debug)]
+pub struct JobSetup {
+    pub job: String,
+    pub source: String,
+    pub remote: String,
+    pub bucket: String,
+    pub remote_setup: RemoteSetup,
+    pub encrypt: bool,
+    pub crypt_password: Option<Secret>,
+    pub crypt_salt: Option<Secret>,
+    pub cron: String,
+    pub scheduler: Scheduler,
+    pub runner: Runner,
+    pub mode: Mode,
+    pub retention: Retention,
+    pub on_overlap: Overlap,
+    pub lock_timeout: u64,
+    pub require_mountpoint: bool,
+    pub sentinel: Option<String>,
+    pub max_drop: Option<u32>,
+    pub max_delete: Option<u64>,
+    pub logs: LogPolicy,
+    pub notify: Option<Notify>,
+    pub credentials: Credentials,
+    pub config_password: Option<PasswordSource>,
+    origins: Vec<(&'static str, String, Origin)>,
+}
+
+impl JobSetup {
+    /// Prints every setting together with the layer it was taken from.
+    pub fn print_origins(&self) {
+        println!("Settings for job '{}':", self.job);
+        for (key, value, origin) in &self.origins {
+            println!("  {} = {:?} ({})", key, value, origin);
+        }
+    }
+}
+
+/// Resolves the jobs `setup` should act on: the one named by `--job`,
+/// otherwise every job in rcloneup.toml, otherwise the default job.
+/// Setting flags need `--job` when the file has jobs.
+pub fn resolve_setup(
+    args: &SetupArgs,
+    matches: &ArgMatches,
+    config: &Config,
+) -> Result<Vec<JobSetup>> {
+    let names: Vec<(String, Origin)> = match &args.job {
+        Some(job) => vec![(job.clone(), cli_origin(matches, "job"))],
+        None if !config.file.jobs.is_empty() => config
+            .file
+            .jobs
+            .keys()
+            .map(|name| (name.clone(), Origin::ConfigFile))
+            .collect(),
+        None => vec![(DEFAULT_JOB.to_string(), Origin::Default)],
+    };
+
+    let jobs: Vec<JobSetup> = names
+        .into_iter()
+        .map(|(job, origin)| resolve_job(args, matches, config, job, origin))
+        .collect::<Result<_>>()?;
+    // A flag given without --job would be copied into every job of the file.
+    if args.job.is_none() && !config.file.jobs.is_empty() {
+        let mut flags: Vec<String> = jobs
+            .iter()
+            .flat_map(|job| &job.origins)
+            .filter(|(key, _, origin)| *origin == Origin::CommandLine && *key != "job")
+            .map(|(key, _, _)| format!("--{}", key.replace('_', "-")))
+            .collect();
+        flags.sort();
+        flags.dedup();
+        if !flags.is_empty() {
+            bail!(
+                "{} would apply to every job in {}; pass --job to pick the job they are for",
+                flags.join(", "),
+                CONFIG_FILE_NAME
+            );
+        }
+    }
+    Ok(jobs)
+}
+
+/// Picks each setting from the highest-precedence layer that has it and
+/// remembers where it came from for `--verbose`.
+struct Layers<'a> {
+    matches: &'a ArgMatches,
+    job: &'a str,
+    origins: Vec<(&'static str, String, Origin)>,
+}
+
+impl Layers<'_> {
+    fn take<T: Clone + fmt::Display>(
+        &mut self,
+        key: &'static str,
+        flag: Option<&T>,
+        file: Option<&T>,
+        default: Option<T>,
+    ) -> Result<T> {
+        let (value, origin) = if let Some(value) = flag {
+            (value.clone(), cli_origin(self.matches, key))
+        } else if let Some(value) = file {
+            (value.clone(), Origin::ConfigFile)
+        } else if let Some(value) = default {
+            (value, Origin::Default)
+        } else {
+            bail!(
+                "Missing '{}' for job '{}': pass --{}, set its environment variable or add it to {}",
+                key,
+                self.job,
+                key.replace('_', "-"),
+                CONFIG_FILE_NAME
+            );
+        };
+        self.origins.push((key, value.to_string(), origin));
+        Ok(value)
+    }
+
+    /// Like `take`, for settings that may be left unset.
+    fn take_optional<T: Clone + fmt::Display>(
+        &mut self,
+        key: &'static str,
+        flag: Option<&T>,
+        file: Option<&T>,
+    ) -> Option<T> {
+        if flag.is_none() && file.is_none() {
+            return None;
+        }
+        self.take(key, flag, file, None).ok()
+    }
+}
+
+fn resolve_job(
+    args: &SetupArgs,
+    matches: &ArgMatches,
+    config: &Config,
+    job: String,
+    job_origin: Origin,
+) -> Result<JobSetup> {
+    let job_config = config.file.jobs.get(&job);
+    let mut layers = Layers {
+        matches,
+        job: &job,
+        origins: vec![("job", job.clone(), job_origin)],
+    };
+
+    let source = layers.take(
+        "source",
+        args.source.as_ref(),
+        job_config.and_then(|j| j.source.as_ref()),
+        None,
+    )?;
+    let remote = layers.take(
+        "remote",
+        args.remote.as_ref(),
+        job_config.and_then(|j| j.remote.as_ref()),
+        Some(
+            args.backend
+                .unwrap_or_default()
+                .default_remote_name()
+                .to_string(),
+        ),
+    )?;
+    let bucket = layers.take(
+        "bucket",
+        args.bucket.as_ref(),
+        job_config.and_then(|j| j.bucket.as_ref()),
+        Some(String::new()),
+    )?;
+    let cron = layers.take(
+        "cron",
+        args.cron.as_ref(),
+        job_config.and_then(|j| j.cron.as_ref()),
+        Some("0 * * * *".to_string()),
+    )?;
+    let scheduler = layers.take(
+        "scheduler",
+        args.scheduler.as_ref(),
+        job_config.and_then(|j| j.scheduler.as_ref()),
+        Some(Scheduler::default()),
+    )?;
+    let runner = layers.take(
+        "runner",
+        args.runner.as_ref(),
+        job_config.and_then(|j| j.runner.as_ref()),
+        Some(Runner::default()),
+    )?;
+    let mode = layers.take(
+        "mode",
+        args.mode.as_ref(),
+        job_config.and_then(|j| j.mode.as_ref()),
+        Some(Mode::default()),
+    )?;
+    let retention = Retention {
+        keep_last: layers.take_optional(
+            "keep_last",
+            args.keep_last.as_ref(),
+            job_config.and_then(|j| j.keep_last.as_ref()),
+        ),
+        keep_daily: layers.take_optional(
+            "keep_daily",
+            args.keep_daily.as_ref(),
+            job_config.and_then(|j| j.keep_daily.as_ref()),
+        ),
+        keep_weekly: layers.take_optional(
+            "keep_weekly",
+            args.keep_weekly.as_ref(),
+            job_config.and_then(|j| j.keep_weekly.as_ref()),
+        ),
+        keep_monthly: layers.take_optional(
+            "keep_monthly",
+            args.keep_monthly.as_ref(),
+            job_config.and_then(|j| j.keep_monthly.as_ref()),
+        ),
+        max_age: layers.take_optional(
+            "max_age",
+            args.max_age.as_ref(),
+            job_config.and_then(|j| j.max_age.as_ref()),
+        ),
+    };
+    let on_overlap = layers.take(
+        "on_overlap",
+        args.on_overlap.as_ref(),
+        job_config.and_then(|j| j.on_overlap.as_ref()),
+        Some(Overlap::default()),
+    )?;
+    let lock_timeout = layers.take(
+        "lock_timeout",
+        args.lock_timeout.as_ref(),
+        job_config.and_then(|j| j.lock_timeout.as_ref()),
+        Some(DEFAULT_LOCK_TIMEOUT),
+    )?;
+    let require_mountpoint = layers.take(
+        "require_mountpoint",
+        args.require_mountpoint.then_some(&true),
+        job_config.and_then(|j| j.require_mountpoint.as_ref()),
+        Some(false),
+    )?;
+    let sentinel = layers.take_optional(
+        "sentinel",
+        args.sentinel.as_ref(),
+        job_config.and_then(|j| j.sentinel.as_ref()),
+    );
+    let max_drop = layers.take_optional(
+        "max_drop",
+        args.max_drop.as_ref(),
+        job_config.and_then(|j| j.max_drop.as_ref()),
+    );
+    let max_delete = layers.take_optional(
+        "max_delete",
+        args.max_delete.as_ref(),
+        job_config.and_then(|j| j.max_delete.as_ref()),
+    );
+    // `rcloneup run` does the rotating, so scripts default to none.
+    let logs = LogPolicy {
+        rotation: layers.take(
+            "log_rotation",
+            args.log_rotation.as_ref(),
+            job_config.and_then(|j| j.log_rotation.as_ref()),
+            Some(match runner {
+                Runner::Binary => LogRotation::Rotate,
+                Runner::Script => LogRotation::Off,
+            }),
+        )?,
+        max_size: layers.take(
+            "log_max_size",
+            args.log_max_size.as_ref(),
+            job_config.and_then(|j| j.log_max_size.as_ref()),
+            Some(DEFAULT_LOG_MAX_SIZE),
+        )?,
+        max_age: layers.take_optional(
+            "log_max_age",
+            args.log_max_age.as_ref(),
+            job_config.and_then(|j| j.log_max_age.as_ref()),
+        ),
+        keep: layers.take(
+            "log_keep",
+            args.log_keep.as_ref(),
+            job_config.and_then(|j| j.log_keep.as_ref()),
+            Some(DEFAULT_LOG_KEEP),
+        )?,
+    };
+    let notify_url = layers.take_optional(
+        "notify_url",
+        args.notify_url.as_ref(),
+        job_config.and_then(|j| j.notify_url.as_ref()),
+    );
+    let notify_preset = layers.take_optional(
+        "notify_preset",
+        args.notify_preset.as_ref(),
+        job_config.and_then(|j| j.notify_preset.as_ref()),
+    );
+    let notify_on = layers.take_optional(
+        "notify_on",
+        args.notify_on.as_ref(),
+        job_config.and_then(|j| j.notify_on.as_ref()),
+    );
+    let notify_template = layers.take_optional(
+        "notify_template",
+        args.notify_template.as_ref(),
+        job_config.and_then(|j| j.notify_template.as_ref()),
+    );
+    let notify = match notify_url {
+        Some(url) => Some(Notify {
+            url,
+            preset: notify_preset.unwrap_or_default(),
+            on: notify_on.unwrap_or_default(),
+            template: notify_template,
+        }),
+        None if notify_preset.is_some() || notify_on.is_some() || notify_template.is_some() => {
+            bail!(
+                "--notify-preset, --notify-on and --notify-template need --notify-url (job '{}')",
+                job
+            )
+        }
+        None => None,
+    };
+
+    let encrypt = layers.take(
+        "encrypt",
+        args.encrypt.then_some(&true),
+        job_config.and_then(|j| j.encrypt.as_ref()),
+        Some(false),
+    )?;
+    let crypt_password = layers.take_optional(
+        "crypt_password",
+        args.crypt_password.as_ref(),
+        job_config.and_then(|j| j.crypt_password.as_ref()),
+    );
+    let crypt_salt = layers.take_optional(
+        "crypt_salt",
+        args.crypt_salt.as_ref(),
+        job_config.and_then(|j| j.crypt_salt.as_ref()),
+    );
+
+    let remote_config = config.file.remotes.get(&remote);
+    let backend = layers.take(
+        "backend",
+        args.backend.as_ref(),
+        remote_config.and_then(|r| r.backend.as_ref()),
+        Some(Backend::default()),
+    )?;
+    let remote_setup = match backend {
+        Backend::S3 => resolve_s3(args, remote_config, &mut layers)?,
+        Backend::Sftp => RemoteSetup::Sftp {
+            host: layers.take(
+                "host",
+                args.host.as_ref(),
+                remote_config.and_then(|r| r.host.as_ref()),
+                None,
+            )?,
+            port: layers.take_optional(
+                "port",
+                args.port.as_ref(),
+                remote_config.and_then(|r| r.port.as_ref()),
+            ),
+            user: layers.take_optional(
+                "user",
+                args.user.as_ref(),
+                remote_config.and_then(|r| r.user.as_ref()),
+            ),
+            key_file: layers.take_optional(
+                "key_file",
+                args.key_file.as_ref(),
+                remote_config.and_then(|r| r.key_file.as_ref()),
+            ),
+            password: layers.take_optional(
+                "password",
+                args.password.as_ref(),
+                remote_config.and_then(|r| r.password.as_ref()),
+            ),
+        },
+        Backend::Webdav => RemoteSetup::Webdav {
+            url: layers.take(
+                "url",
+                args.url.as_ref(),
+                remote_config.and_then(|r| r.url.as_ref()),
+                None,
+            )?,
+            vendor: layers.take(
+                "webdav_vendor",
+                args.webdav_vendor.as_ref(),
+                remote_config.and_then(|r| r.webdav_vendor.as_ref()),
+                Some("other".to_string()),
+            )?,
+            user: layers.take_optional(
+                "user",
+                args.user.as_ref(),
+                remote_config.and_then(|r| r.user.as_ref()),
+            ),
+            password: layers.take_optional(
+                "password",
+                args.password.as_ref(),
+                remote_config.and_then(|r| r.password.as_ref()),
+            ),
+        },
+        Backend::Local => RemoteSetup::Local,
+    };
+
+    let options = &config.file.options;
+    let credentials = layers.take(
+        "credentials",
+        args.credentials.as_ref(),
+        options.credentials.as_ref(),
+        Some(Credentials::default()),
+    )?;
+    let password_file = layers.take_optional(
+        "config_password_file",
+        args.config_password_file.as_ref(),
+        options.config_password_file.as_ref(),
+    );
+    let password_command = layers.take_optional(
+        "config_password_command",
+        args.config_password_command.as_ref(),
+        options.config_password_command.as_ref(),
+    );
+    let config_password = match (password_file, password_command) {
+        (Some(_), Some(_)) => bail!(
+            "Only one of config_password_file and config_password_command may be set for job '{}'",
+            job
+        ),
+        (Some(file), None) => Some(PasswordSource::File(file.into())),
+        (None, Some(command)) => Some(PasswordSource::Command(command)),
+        (None, None) => None,
+    };
+
+    Ok(JobSetup {
+        origins: layers.origins,
+        job,
+        source,
+        remote,
+        bucket,
+        remote_setup,
+        encrypt,
+        crypt_password,
+        crypt_salt,
+        cron,
+        scheduler,
+        runner,
+        mode,
+        retention,
+        on_overlap,
+        lock_timeout,
+        require_mountpoint,
+        sentinel,
+        max_drop,
+        max_delete,
+        logs,
+        notify,
+        credentials,
+        config_password,
+    })
+}
+
+fn resolve_s3(
+    args: &SetupArgs,
+    remote_config: Option<&RemoteConfig>,
+    layers: &mut Layers,
+) -> Result<RemoteSetup> {
+    let provider = layers.take(
+        "provider",
+        args.provider.as_ref(),
+        remote_config.and_then(|r| r.provider.as_ref()),
+        Some(Provider::default()),
+    )?;
+    let region_flag = args.region.as_ref();
+    let region_file = remote_config.and_then(|r| r.region.as_ref());
+    let region = match provider.default_region() {
+        Some(default) => Some(layers.take(
+            "region",
+            region_flag,
+            region_file,
+            Some(default.to_string()),
+        )?),
+        None => layers.take_optional("region", region_flag, region_file),
+    };
+    let account_id = layers.take_optional(
+        "account_id",
+        args.account_id.as_ref(),
+        remote_config.and_then(|r| r.account_id.as_ref()),
+    );
+    let location_constraint = layers.take_optional(
+        "location_constraint",
+        args.location_constraint.as_ref(),
+        remote_config.and_then(|r| r.location_constraint.as_ref()),
+    );
+    // Endpoints that can be derived from the region or account are
+    // defaults; validate_args reports the ones that are still missing.
+    let endpoint_flag = args.endpoint.as_ref();
+    let endpoint_file = remote_config.and_then(|r| r.endpoint.as_ref());
+    let endpoint = match provider.default_endpoint(region.as_deref(), account_id.as_deref()) {
+        Some(default) => {
+            Some(layers.take("endpoint", endpoint_flag, endpoint_file, Some(default))?)
+        }
+        None => layers.take_optional("endpoint", endpoint_flag, endpoint_file),
+    };
+    let access_key = layers.take(
+        "access_key",
+        args.access_key.as_ref(),
+        remote_config.and_then(|r| r.access_key.as_ref()),
+        None,
+    )?;
+    let secret_key = layers.take(
+        "secret_key",
+        args.secret_key.as_ref(),
+        remote_config.and_then(|r| r.secret_key.as_ref()),
+        None,
+    )?;
+    Ok(RemoteSetup::S3 {
+        provider,
+        endpoint,
+        region,
+        account_id,
+        location_constraint,
+        access_key,
+        secret_key,
+    })
+}
+
+fn cli_origin(matches: &ArgMatches, id: &str) -> Origin {
+    match matches.value_source(id) {
+        Some(ValueSource::EnvVariable) => Origin::Environment,
+        _ => Origin::CommandLine,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing;
+
+    const FILE: &str = r#"
+[remotes.minio]
+access_key = "a"
+secret_key = "b"
+
+[jobs.photos]
+source = "/srv/photos"
+bucket = "photos"
+
+[jobs.db]
+source = "/srv/db"
+bucket = "db"
+cron = "30 2 * * *"
+"#;
+
+    #[test]
+    fn without_job_every_job_in_the_file_is_set_up() {
+        let jobs = testing::setup_jobs(&[], FILE).unwrap();
+        let found: Vec<_> = jobs
+            .iter()
+            .map(|j| (j.job.as_str(), j.source.as_str(), j.cron.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("db", "/srv/db", "30 2 * * *"),
+                ("photos", "/srv/photos", "0 * * * *")
+            ]
+        );
+    }
+
+    #[test]
+    fn flags_without_job_are_refused_when_the_file_has_jobs() {
+        let err = testing::setup_jobs(&["--cron", "@daily", "--source", "/x"], FILE).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "--cron, --source would apply to every job in rcloneup.toml; pass --job to pick the job they are for"
+        );
+    }
+
+    #[test]
+    fn flags_with_job_change_only_that_job() {
+        let jobs = testing::setup_jobs(&["--job", "db", "--cron", "@daily"], FILE).unwrap();
+        assert_eq!(jobs.len(), 1);
+        assert_eq!(jobs[0].job, "db");
+        assert_eq!(jobs[0].cron, "@daily");
+        assert_eq!(jobs[0].bucket, "db");
+    }
+
+    #[test]
+    fn flags_without_a_file_set_up_the_default_job() {
+        let args = ["--source", "/x", "--access-key", "a", "--secret-key", "b"];
+        let jobs = testing::setup_jobs(&args, "").unwrap();
+        assert_eq!(jobs.len(), 1);
+        assert_eq!(jobs[0].job, "default");
+        assert_eq!(jobs[0].source, "/x");
+    }
+
+    #[test]
+    fn verbose_origins_mask_secrets() {
+        let args = [
+            "--source",
+            "/x",
+            "--access-key",
+            "AKIA123",
+            "--secret-key",
+            "hunter2",
+            "--crypt-password",
+            "crypt-pw",
+            "--encrypt",
+        ];
+        let jobs = testing::setup_jobs(&args, "").unwrap();
+        let shown = format!("{:?}", jobs[0]);
+        for secret in ["AKIA123", "hunter2", "crypt-pw"] {
+            assert!(!shown.contains(secret), "{} in {}", secret, shown);
+            assert!(!jobs[0]
+                .origins
+                .iter()
+                .any(|(_, value, _)| value.contains(secret)));
+        }
+    }
+}
@@ -0,0 +1,442 @@
+use crate::shell;
+use anyhow::{anyhow, bail, Context, Result};
+use std::{
+    io::{self, Write},
+    path::Path,
+    process::{Command, Stdio},
+};
+
+fn begin_marker(job: &str) -> String {
+    format!("# BEGIN rcloneup:{}", job)
+}
+
+fn end_marker(job: &str) -> String {
+    format!("# END rcloneup:{}", job)
+}
+
+fn unmatched(found: String, missing: String) -> anyhow::Error {
+    anyhow!(
+        "Crontab has '{}' without a matching '{}'; fix it with 'crontab -e' and try again",
+        found,
+        missing
+    )
+}
+
+/// The user's crontab, split into lines that keep their own line endings
+/// so that everything outside our blocks is written back byte-for-byte.
+#[derive(Debug)]
+struct Crontab {
+    lines: Vec<String>,
+}
+
+impl Crontab {
+    /// Reads `crontab -l`. Only a user without a crontab starts from an
+    /// empty one; any other failure stops before the crontab is rewritten
+    /// without the user's entries.
+    fn read(verbose: bool) -> Result<Self> {
+        let text = match Command::new("crontab").arg("-l").output() {
+            Ok(out) if out.status.success() => {
+                String::from_utf8(out.stdout).context("Existing crontab is not valid UTF-8")?
+            }
+            Ok(out) => {
+                let stderr = String::from_utf8_lossy(&out.stderr);
+                if !stderr.contains("no crontab for") {
+                    bail!("'crontab -l' failed with {}: {}", out.status, stderr.trim());
+                }
+                if verbose {
+                    println!("No existing crontab found, starting fresh.");
+                }
+                String::new()
+            }
+            // Without cron there is no crontab to keep; writing one fails.
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                if verbose {
+                    println!("crontab is not installed, no existing cron jobs.");
+                }
+                String::new()
+            }
+            Err(e) => return Err(e).context("Failed to run 'crontab -l'"),
+        };
+        Self::parse(&text)
+    }
+
+    /// Splits `text` into lines, refusing markers that do not pair up: with
+    /// a BEGIN and no END, a rewrite could not tell where the block stops.
+    fn parse(text: &str) -> Result<Self> {
+        let lines: Vec<String> = text.split_inclusive('\n').map(str::to_string).collect();
+        let mut open: Option<&str> = None;
+        let mut seen = Vec::new();
+        for line in &lines {
+            let line = line.trim_end();
+            if let Some(job) = line.strip_prefix("# BEGIN rcloneup:") {
+                if let Some(open) = open {
+                    return Err(unmatched(begin_marker(open), end_marker(open)));
+                }
+                if seen.contains(&job) {
+                    bail!(
+                        "Crontab has more than one '{}' block; fix it with 'crontab -e' and try again",
+                        begin_marker(job)
+                    );
+                }
+                seen.push(job);
+                open = Some(job);
+            } else if let Some(job) = line.strip_prefix("# END rcloneup:") {
+                if open != Some(job) {
+                    return Err(unmatched(end_marker(job), begin_marker(job)));
+                }
+                open = None;
+            }
+        }
+        if let Some(open) = open {
+            return Err(unmatched(begin_marker(open), end_marker(open)));
+        }
+        Ok(Self { lines })
+    }
+
+    fn render(&self) -> String {
+        self.lines.concat()
+    }
+
+    fn write(&self, verbose: bool) -> Result<()> {
+        if verbose {
+            println!("New crontab lines:");
+            for line in &self.lines {
+                println!("  {}", line.trim_end_matches('\n'));
+            }
+        }
+
+        let mut crontab_process = Command::new("crontab")
+            .stdin(Stdio::piped())
+            .spawn()
+            .context("Failed to spawn crontab command")?;
+
+        crontab_process
+            .stdin
+            .as_mut()
+            .context("Failed to open stdin")?
+            .write_all(self.render().as_bytes())?;
+
+        let status = crontab_process.wait()?;
+        if !status.success() {
+            bail!("Failed to install new crontab");
+        }
+        Ok(())
+    }
+
+    /// Index range of `job`'s block, from its BEGIN line to its END line inclusive.
+    fn block(&self, job: &str) -> Option<(usize, usize)> {
+        let begin = begin_marker(job);
+        let end = end_marker(job);
+        let start = self.lines.iter().position(|l| l.trim_end() == begin)?;
+        let len = self.lines[start..]
+            .iter()
+            .position(|l| l.trim_end() == end)?;
+        Some((start, start + len))
+    }
+
+    /// Lines outside any rcloneup block that run `script_path`, as installed
+    /// by releases that did not write markers.
+    fn unmarked_entries(&self, script_path: &Path) -> Vec<usize> {
+        let script = script_path.to_string_lossy();
+        let mut in_block = false;
+        let mut found = Vec::new();
+        for (i, line) in self.lines.iter().enumerate() {
+            let line = line.trim_end();
+            if line.starts_with("# BEGIN rcloneup:") {
+                in_block = true;
+            } else if line.starts_with("# END rcloneup:") {
+                in_block = false;
+            } else if !in_block
+                && !line.trim_start().starts_with('#')
+                && line
+                    .strip_suffix(script.as_ref())
+                    .is_some_and(|rest| rest.ends_with(char::is_whitespace))
+            {
+                found.push(i);
+            }
+        }
+        found
+    }
+
+    /// Puts `line` in `job`'s block, replacing the block if it exists and
+    /// appending it otherwise. Unmarked lines running `script_path` are
+    /// removed and returned.
+    fn install(&mut self, job: &str, line: &str, script_path: &Path) -> Vec<String> {
+        let block = vec![
+            format!("{}\n", begin_marker(job)),
+            format!("{}\n", line),
+            format!("{}\n", end_marker(job)),
+        ];
+        match self.block(job) {
+            Some((start, end)) => {
+                self.lines.splice(start..=end, block);
+            }
+            None => {
+                if let Some(last) = self.lines.last_mut() {
+                    if !last.ends_with('\n') {
+                        last.push('\n');
+                    }
+                }
+                self.lines.extend(block);
+            }
+        }
+        let mut removed = Vec::new();
+        for i in self.unmarked_entries(script_path).into_iter().rev() {
+            removed.insert(0, self.lines.remove(i));
+        }
+        removed
+    }
+
+    /// Removes `job`'s block and any unmarked line running `script_path`,
+    /// returning the removed lines.
+    fn remove(&mut self, job: Option<&str>, script_path: &Path) -> Vec<String> {
+        let mut removed = Vec::new();
+        if let Some((start, end)) = job.and_then(|job| self.block(job)) {
+            removed.extend(self.lines.drain(start..=end));
+        }
+        for i in self.unmarked_entries(script_path).into_iter().rev() {
+            removed.insert(0, self.lines.remove(i));
+        }
+        removed
+    }
+}
+
+/// The line inside a job's block that runs `command` on `cron_schedule`.
+pub fn cron_line(command: &[String], cron_schedule: &str) -> String {
+    format!(
+        "{} {}",
+        cron_schedule,
+        command
+            .iter()
+            .map(|word| shell::quote_crontab(word))
+            .collect::<Vec<_>>()
+            .join(" ")
+    )
+}
+
+/// Installs `job`'s block running `command`. Unmarked lines left for the
+/// job's `script_path` by older releases are dropped.
+pub fn update_cron_job(
+    job: &str,
+    command: &[String],
+    script_path: &Path,
+    cron_schedule: &str,
+    verbose: bool,
+) -> Result<()> {
+    if verbose {
+        println!("Updating crontab...");
+    }
+
+    let mut crontab = Crontab::read(verbose)?;
+    let original = crontab.render();
+
+    for line in crontab.install(job, &cron_line(command, cron_schedule), script_path) {
+        if verbose {
+            println!("Removing unmarked cron job line: {}", line.trim_end());
+        }
+    }
+
+    if crontab.render() == original {
+        if verbose {
+            println!("Crontab up to date.");
+        }
+        return Ok(());
+    }
+    crontab.write(verbose)?;
+
+    if verbose {
+        println!("Crontab updated successfully.");
+    }
+    Ok(())
+}
+
+fn remove_entries(
+    job: Option<&str>,
+    script_path: &Path,
+    dry_run: bool,
+    verbose: bool,
+) -> Result<()> {
+    let mut crontab = Crontab::read(verbose)?;
+    let removed = crontab.remove(job, script_path);
+    if removed.is_empty() {
+        if verbose {
+            println!("No cron job found for {}", script_path.display());
+        }
+        return Ok(());
+    }
+    for line in &removed {
+        let prefix = if dry_run {
+            "(dry-run) Would remove"
+        } else {
+            "Removing"
+        };
+        println!("{} cron job line: {}", prefix, line.trim_end());
+    }
+    if !dry_run {
+        crontab.write(verbose)?;
+    }
+    Ok(())
+}
+
+/// Removes `job`'s marked block, plus any unmarked line left for its script.
+pub fn remove_cron_job(job: &str, script_path: &Path, dry_run: bool, verbose: bool) -> Result<()> {
+    remove_entries(Some(job), script_path, dry_run, verbose)
+}
+
+/// Removes unmarked lines running the pre-jobs `~/rclone_backup.sh`.
+pub fn remove_legacy_cron_job(script_path: &Path, dry_run: bool, verbose: bool) -> Result<()> {
+    remove_entries(None, script_path, dry_run, verbose)
+}
+
+/// Returns the schedule line inside `job`'s block, if it is installed.
+pub fn find_cron_job(job: &str) -> Result<Option<String>> {
+    let crontab = Crontab::read(false)?;
+    Ok(crontab.block(job).and_then(|(start, end)| {
+        crontab.lines[start + 1..end]
+            .iter()
+            .map(|l| l.trim_end().to_string())
+            .find(|l| !l.is_empty() && !l.starts_with('#'))
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::Command;
+
+    /// What cron hands to sh: `%` ends the command unless escaped, and
+    /// the backslash in front of an escaped one is dropped.
+    fn cron_command(line: &str) -> String {
+        let command = line.splitn(6, ' ').last().unwrap();
+        let mut out = String::new();
+        let mut chars = command.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' if chars.peek() == Some(&'%') => out.push(chars.next().unwrap()),
+                '%' => break,
+                c => out.push(c),
+            }
+        }
+        out
+    }
+
+    #[test]
+    fn cron_line_survives_cron_and_the_shell() {
+        let source = "/data/it's \"a\" $HOME `id` 100% done\\";
+        let command = [
+            "printf".to_string(),
+            "%s\\0".to_string(),
+            source.to_string(),
+        ];
+        let line = cron_line(&command, "0 3 * * *");
+        assert!(line.starts_with("0 3 * * * 'printf' "));
+        let output = Command::new("sh")
+            .arg("-c")
+            .arg(cron_command(&line))
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        assert_eq!(
+            String::from_utf8(output.stdout).unwrap(),
+            format!("{}\0", source)
+        );
+    }
+
+    #[test]
+    fn cron_line_escapes_every_percent() {
+        let line = cron_line(&["/bin/rcloneup".to_string(), "%%a%".to_string()], "@daily");
+        assert_eq!(line, r"@daily '/bin/rcloneup' '\%\%a\%'");
+    }
+
+    const SCRIPT: &str = "/home/u/.local/state/rcloneup/jobs/default/backup.sh";
+
+    fn installed(text: &str, job: &str, line: &str) -> String {
+        let mut crontab = Crontab::parse(text).unwrap();
+        crontab.install(job, line, Path::new(SCRIPT));
+        crontab.render()
+    }
+
+    #[test]
+    fn install_keeps_other_lines_byte_for_byte() {
+        let text = "MAILTO=me\r\n# nightly\n0 1 * * *  /usr/bin/other   --flag\n\n# trailing comment without newline";
+        let crontab = Crontab::parse(text).unwrap();
+        assert_eq!(crontab.render(), text);
+
+        let added = installed(text, "default", "0 * * * * /bin/run");
+        assert_eq!(
+            added,
+            format!(
+                "{}\n# BEGIN rcloneup:default\n0 * * * * /bin/run\n# END rcloneup:default\n",
+                text
+            )
+        );
+        let mut crontab = Crontab::parse(&added).unwrap();
+        let removed = crontab.remove(Some("default"), Path::new(SCRIPT));
+        assert_eq!(removed.len(), 3);
+        assert_eq!(crontab.render(), format!("{}\n", text));
+    }
+
+    #[test]
+    fn install_and_remove_touch_only_their_own_job() {
+        let text = "# BEGIN rcloneup:a\n0 1 * * * /bin/a\n# END rcloneup:a\n\
+                    5 5 * * * /usr/bin/mine\n\
+                    # BEGIN rcloneup:b\n0 2 * * * /bin/b\n# END rcloneup:b\n";
+
+        let updated = installed(text, "a", "30 1 * * * /bin/a");
+        assert_eq!(
+            updated,
+            text.replace("0 1 * * * /bin/a", "30 1 * * * /bin/a")
+        );
+        assert_eq!(installed(&updated, "a", "30 1 * * * /bin/a"), updated);
+
+        let with_c = installed(text, "c", "0 3 * * * /bin/c");
+        assert_eq!(
+            with_c,
+            format!(
+                "{}# BEGIN rcloneup:c\n0 3 * * * /bin/c\n# END rcloneup:c\n",
+                text
+            )
+        );
+
+        let mut crontab = Crontab::parse(&with_c).unwrap();
+        crontab.remove(Some("b"), Path::new(SCRIPT));
+        assert_eq!(
+            crontab.render(),
+            "# BEGIN rcloneup:a\n0 1 * * * /bin/a\n# END rcloneup:a\n\
+             5 5 * * * /usr/bin/mine\n\
+             # BEGIN rcloneup:c\n0 3 * * * /bin/c\n# END rcloneup:c\n"
+        );
+    }
+
+    #[test]
+    fn install_replaces_unmarked_lines_for_the_script() {
+        let text = format!("0 2 * * * {}\n# 0 2 * * * {}\n", SCRIPT, SCRIPT);
+        let mut crontab = Crontab::parse(&text).unwrap();
+        let removed = crontab.install("default", "0 * * * * /bin/run", Path::new(SCRIPT));
+        assert_eq!(removed, [format!("0 2 * * * {}\n", SCRIPT)]);
+        assert_eq!(
+            crontab.render(),
+            format!(
+                "# 0 2 * * * {}\n# BEGIN rcloneup:default\n0 * * * * /bin/run\n# END rcloneup:default\n",
+                SCRIPT
+            )
+        );
+    }
+
+    #[test]
+    fn unbalanced_markers_are_refused() {
+        for text in [
+            "# BEGIN rcloneup:default\n0 * * * * /bin/run\n",
+            "0 * * * * /bin/run\n# END rcloneup:default\n",
+            "# BEGIN rcloneup:a\n# BEGIN rcloneup:b\n# END rcloneup:b\n# END rcloneup:a\n",
+            "# BEGIN rcloneup:a\n# END rcloneup:b\n",
+            "# BEGIN rcloneup:a\n# END rcloneup:a\n# BEGIN rcloneup:a\n# END rcloneup:a\n",
+        ] {
+            assert!(Crontab::parse(text).is_err(), "accepted {:?}", text);
+        }
+        let err = Crontab::parse("# BEGIN rcloneup:default\n").unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("'# BEGIN rcloneup:default' without a matching '# END rcloneup:default'"));
+    }
+}
@@ -0,0 +1,179 @@
+use anyhow::{Context, Result};
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    os::unix::fs::{OpenOptionsExt, PermissionsExt},
+    path::{Path, PathBuf},
+    process,
+};
+
+/// Writes `content` to `path` unless it already matches, returning whether
+/// it wrote. The file always ends up with mode `perms`, also when the
+/// content was already right. New content goes to a temp file created
+/// with `perms` and renamed into place, so secrets are never readable
+/// under looser permissions and a crash never leaves half a file.
+pub fn write_if_changed(path: &Path, content: &[u8], perms: u32, verbose: bool) -> Result<bool> {
+    // Write through a symlink, e.g. an rclone.conf kept in a dotfiles repo.
+    let path = &match fs::canonicalize(path) {
+        Ok(target) => target,
+        Err(_) => path.to_path_buf(),
+    };
+    let need_write = match fs::read(path) {
+        Ok(existing) => existing != content,
+        Err(_) => true,
+    };
+
+    if need_write {
+        if verbose {
+            println!("Writing file: {}", path.display());
+        }
+        let tmp = temp_path(path);
+        let _ = fs::remove_file(&tmp);
+        let result = write_new(&tmp, content, perms)
+            .and_then(|()| fs::rename(&tmp, path).map_err(Into::into));
+        if result.is_err() {
+            let _ = fs::remove_file(&tmp);
+        }
+        result.with_context(|| format!("Failed to write {}", path.display()))?;
+    } else {
+        let mode = fs::metadata(path)?.permissions().mode() & 0o7777;
+        if mode != perms {
+            fs::set_permissions(path, fs::Permissions::from_mode(perms))
+                .with_context(|| format!("Failed to set permissions of {}", path.display()))?;
+            println!(
+                "Changed permissions of {} from {:o} to {:o}",
+                path.display(),
+                mode,
+                perms
+            );
+        } else if verbose {
+            println!("File up to date: {}", path.display());
+        }
+    }
+    Ok(need_write)
+}
+
+/// A sibling of `path` to write to before renaming over it.
+fn temp_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".rcloneup-{}.tmp", process::id()));
+    path.with_file_name(name)
+}
+
+/// Creates `path`, which must not exist, with `perms` and `content`.
+fn write_new(path: &Path, content: &[u8], perms: u32) -> Result<()> {
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(perms)
+        .open(path)?;
+    // The umask may have taken bits away; set exactly what was asked for.
+    file.set_permissions(fs::Permissions::from_mode(perms))?;
+    file.write_all(content)?;
+    file.sync_all()?;
+    Ok(())
+}
+
+pub fn remove_if_exists(path: &Path, dry_run: bool, verbose: bool) -> Result<()> {
+    if !path.exists() {
+        if verbose {
+            println!("Already absent: {}", path.display());
+        }
+        return Ok(());
+    }
+    if dry_run {
+        println!("(dry-run) Would remove file: {}", path.display());
+        return Ok(());
+    }
+    fs::remove_file(path).with_context(|| format!("Failed to remove {}", path.display()))?;
+    println!("Removed file: {}", path.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+    use std::os::unix::fs::symlink;
+
+    fn mode(path: &Path) -> u32 {
+        fs::metadata(path).unwrap().permissions().mode() & 0o7777
+    }
+
+    fn entries(dir: &Path) -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn creates_new_files_with_the_given_mode() {
+        let dir = TempDir::new();
+        let path = dir.path().join("secret.txt");
+        assert!(write_if_changed(&path, b"key", 0o600, false).unwrap());
+        assert_eq!(fs::read(&path).unwrap(), b"key");
+        assert_eq!(mode(&path), 0o600);
+        assert_eq!(entries(dir.path()), ["secret.txt"]);
+    }
+
+    #[test]
+    fn replaces_changed_content_with_the_given_mode() {
+        let dir = TempDir::new();
+        let path = dir.path().join("rclone.conf");
+        fs::write(&path, "old").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+        assert!(write_if_changed(&path, b"new", 0o600, false).unwrap());
+        assert_eq!(fs::read(&path).unwrap(), b"new");
+        assert_eq!(mode(&path), 0o600);
+        assert_eq!(entries(dir.path()), ["rclone.conf"]);
+    }
+
+    #[test]
+    fn tightens_loose_permissions_of_unchanged_files() {
+        let dir = TempDir::new();
+        let path = dir.path().join("recovery.txt");
+        fs::write(&path, "same").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o666)).unwrap();
+        assert!(!write_if_changed(&path, b"same", 0o600, false).unwrap());
+        assert_eq!(mode(&path), 0o600);
+    }
+
+    #[test]
+    fn writes_through_symlinks() {
+        let dir = TempDir::new();
+        let target = dir.path().join("dotfiles.conf");
+        let link = dir.path().join("rclone.conf");
+        fs::write(&target, "old").unwrap();
+        symlink(&target, &link).unwrap();
+        assert!(write_if_changed(&link, b"new", 0o600, false).unwrap());
+        assert!(fs::symlink_metadata(&link)
+            .unwrap()
+            .file_type()
+            .is_symlink());
+        assert_eq!(fs::read(&target).unwrap(), b"new");
+        assert_eq!(mode(&target), 0o600);
+    }
+
+    #[test]
+    fn failed_writes_leave_no_temp_file() {
+        let dir = TempDir::new();
+        let path = dir.path().join("missing-dir").join("file");
+        assert!(write_if_changed(&path, b"x", 0o600, false).is_err());
+        assert!(entries(dir.path()).is_empty());
+    }
+
+    #[test]
+    fn remove_if_exists_respects_dry_run() {
+        let dir = TempDir::new();
+        let path = dir.path().join("file");
+        fs::write(&path, "x").unwrap();
+        remove_if_exists(&path, true, false).unwrap();
+        assert!(path.exists());
+        remove_if_exists(&path, false, false).unwrap();
+        assert!(!path.exists());
+        remove_if_exists(&path, false, false).unwrap();
+    }
+}
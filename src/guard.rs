@@ -0,0 +1,299 @@
+use crate::{job::Job, paths::JobPaths};
+use anyhow::{Context, Result};
+use chrono::Local;
+use std::{
+    error::Error,
+    fmt, fs,
+    io::Write,
+    os::unix::fs::MetadataExt,
+    path::{Path, PathBuf},
+};
+
+/// A pre-flight check that stopped a run before rclone could delete
+/// anything on the remote. Each kind exits with its own status code.
+#[derive(Debug)]
+pub enum GuardError {
+    SourceMissing(String),
+    SourceEmpty(String),
+    NotMountpoint(String),
+    SentinelMissing(PathBuf),
+    FileCountDrop {
+        previous: u64,
+        current: u64,
+        max_drop: u32,
+    },
+}
+
+impl GuardError {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            GuardError::SourceMissing(_) => 10,
+            GuardError::SourceEmpty(_) => 11,
+            GuardError::NotMountpoint(_) => 12,
+            GuardError::SentinelMissing(_) => 13,
+            GuardError::FileCountDrop { .. } => 14,
+        }
+    }
+}
+
+impl fmt::Display for GuardError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GuardError::SourceMissing(source) => {
+                write!(f, "Backup source {} does not exist", source)
+            }
+            GuardError::SourceEmpty(source) => write!(f, "Backup source {} is empty", source),
+            GuardError::NotMountpoint(source) => {
+                write!(f, "Backup source {} is not a mountpoint", source)
+            }
+            GuardError::SentinelMissing(path) => {
+                write!(f, "Sentinel file {} is missing", path.display())
+            }
+            GuardError::FileCountDrop {
+                previous,
+                current,
+                max_drop,
+            } => write!(
+                f,
+                "Backup source has {} files, down from {} on the last run (more than {}% fewer)",
+                current, previous, max_drop
+            ),
+        }
+    }
+}
+
+impl Error for GuardError {}
+
+/// Checks that syncing `job`'s source cannot wipe the remote by accident.
+/// Returns the source's file count when `max_drop` needs it recorded.
+pub fn check(job: &Job, job_paths: &JobPaths) -> Result<Option<u64>> {
+    let result = run_checks(job, job_paths);
+    if let Err(e) = &result {
+        if let Some(guard) = e.downcast_ref::<GuardError>() {
+            // The guard's exit code matters more than the log line.
+            let message = format!("Refusing to back up: {}", guard);
+            if let Err(e) = log(&job_paths.log_file, &message) {
+                eprintln!("Warning: {:#}", e);
+            }
+        }
+    }
+    result
+}
+
+fn run_checks(job: &Job, job_paths: &JobPaths) -> Result<Option<u64>> {
+    let source = Path::new(&job.source);
+    if !source.is_dir() {
+        return Err(GuardError::SourceMissing(job.source.clone()).into());
+    }
+    if job.require_mountpoint && !is_mountpoint(source)? {
+        return Err(GuardError::NotMountpoint(job.source.clone()).into());
+    }
+    if let Some(sentinel) = &job.sentinel {
+        let path = source.join(sentinel);
+        if !path.exists() {
+            return Err(GuardError::SentinelMissing(path).into());
+        }
+    }
+    let is_empty = fs::read_dir(source)
+        .with_context(|| format!("Failed to read {}", job.source))?
+        .next()
+        .is_none();
+    if is_empty {
+        return Err(GuardError::SourceEmpty(job.source.clone()).into());
+    }
+
+    let Some(max_drop) = job.max_drop else {
+        return Ok(None);
+    };
+    let current = count_files(source)?;
+    if let Some(previous) = last_file_count(job_paths)? {
+        let dropped = previous.saturating_sub(current);
+        if previous > 0 && dropped * 100 > previous * u64::from(max_drop) {
+            return Err(GuardError::FileCountDrop {
+                previous,
+                current,
+                max_drop,
+            }
+            .into());
+        }
+    }
+    Ok(Some(current))
+}
+
+/// Remembers the file count of a successful run for the next `max_drop` check.
+pub fn record_file_count(job_paths: &JobPaths, count: u64) -> Result<()> {
+    fs::write(&job_paths.file_count, format!("{}\n", count)).with_context(|| {
+        format!(
+            "Failed to write file count to {}",
+            job_paths.file_count.display()
+        )
+    })
+}
+
+fn last_file_count(job_paths: &JobPaths) -> Result<Option<u64>> {
+    if !job_paths.file_count.exists() {
+        return Ok(None);
+    }
+    let text = fs::read_to_string(&job_paths.file_count)?;
+    let count = text
+        .trim()
+        .parse()
+        .with_context(|| format!("Invalid file count in {}", job_paths.file_count.display()))?;
+    Ok(Some(count))
+}
+
+fn is_mountpoint(path: &Path) -> Result<bool> {
+    let path = path
+        .canonicalize()
+        .with_context(|| format!("Failed to resolve {}", path.display()))?;
+    let Some(parent) = path.parent() else {
+        // The root directory is always a mountpoint.
+        return Ok(true);
+    };
+    let dev = fs::metadata(&path)?.dev();
+    let parent_dev = fs::metadata(parent)?.dev();
+    Ok(dev != parent_dev)
+}
+
+/// Counts files below `dir` without following symlinks.
+fn count_files(dir: &Path) -> Result<u64> {
+    let mut count = 0;
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        for entry in
+            fs::read_dir(&dir).with_context(|| format!("Failed to read {}", dir.display()))?
+        {
+            let entry = entry?;
+            if entry.file_type()?.is_dir() {
+                pending.push(entry.path());
+            } else {
+                count += 1;
+            }
+        }
+    }
+    Ok(count)
+}
+
+/// Appends a line to the job's log in the same JSON shape as rclone's own.
+fn log(log_file: &Path, message: &str) -> Result<()> {
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(log_file)
+        .with_context(|| format!("Failed to open {}", log_file.display()))?;
+    let line = serde_json::json!({
+        "time": Local::now().to_rfc3339(),
+        "level": "error",
+        "msg": format!("rcloneup: {}", message),
+        "source": "rcloneup",
+    });
+    writeln!(file, "{}", line)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, TempDir};
+
+    /// A job whose source below `dir` holds `files` files.
+    fn setup(dir: &TempDir, files: usize) -> (Job, JobPaths) {
+        let source = dir.path().join("source");
+        fs::create_dir_all(source.join("sub")).unwrap();
+        for i in 0..files {
+            fs::write(source.join("sub").join(i.to_string()), "").unwrap();
+        }
+        let job_paths = dir.paths().job("default");
+        fs::create_dir_all(&job_paths.dir).unwrap();
+        let job = testing::job("default", &source.to_string_lossy());
+        (job, job_paths)
+    }
+
+    fn exit_code(result: Result<Option<u64>>) -> i32 {
+        result
+            .unwrap_err()
+            .downcast_ref::<GuardError>()
+            .expect("a guard error")
+            .exit_code()
+    }
+
+    #[test]
+    fn missing_and_empty_sources_are_refused() {
+        let dir = TempDir::new();
+        let (job, job_paths) = setup(&dir, 0);
+        let missing = Job {
+            source: dir.path().join("gone").to_string_lossy().into_owned(),
+            ..job.clone()
+        };
+        assert_eq!(exit_code(check(&missing, &job_paths)), 10);
+
+        fs::remove_dir(Path::new(&job.source).join("sub")).unwrap();
+        assert_eq!(exit_code(check(&job, &job_paths)), 11);
+
+        let log = fs::read_to_string(&job_paths.log_file).unwrap();
+        assert_eq!(log.lines().count(), 2);
+        assert!(log.contains("Refusing to back up: Backup source"));
+    }
+
+    #[test]
+    fn mountpoint_guard_requires_a_mounted_source() {
+        let dir = TempDir::new();
+        let (job, job_paths) = setup(&dir, 1);
+        let job = Job {
+            require_mountpoint: true,
+            ..job
+        };
+        assert_eq!(exit_code(check(&job, &job_paths)), 12);
+
+        let root = Job {
+            source: "/".to_string(),
+            ..job
+        };
+        assert_eq!(check(&root, &job_paths).unwrap(), None);
+    }
+
+    #[test]
+    fn sentinel_guard_requires_the_file() {
+        let dir = TempDir::new();
+        let (job, job_paths) = setup(&dir, 1);
+        let job = Job {
+            sentinel: Some(".backup-me".to_string()),
+            ..job
+        };
+        assert_eq!(exit_code(check(&job, &job_paths)), 13);
+
+        fs::write(Path::new(&job.source).join(".backup-me"), "").unwrap();
+        assert_eq!(check(&job, &job_paths).unwrap(), None);
+    }
+
+    #[test]
+    fn max_drop_guard_compares_with_the_last_count() {
+        let dir = TempDir::new();
+        let (job, job_paths) = setup(&dir, 5);
+        let job = Job {
+            max_drop: Some(40),
+            ..job
+        };
+        // No previous count: the current one is returned for recording.
+        assert_eq!(check(&job, &job_paths).unwrap(), Some(5));
+
+        record_file_count(&job_paths, 10).unwrap();
+        assert_eq!(exit_code(check(&job, &job_paths)), 14);
+
+        let job = Job {
+            max_drop: Some(50),
+            ..job
+        };
+        assert_eq!(check(&job, &job_paths).unwrap(), Some(5));
+    }
+
+    #[test]
+    fn guard_error_survives_a_failing_log() {
+        let dir = TempDir::new();
+        let (job, job_paths) = setup(&dir, 0);
+        fs::remove_dir(Path::new(&job.source).join("sub")).unwrap();
+        // Opening a directory for appending fails.
+        fs::create_dir(&job_paths.log_file).unwrap();
+        assert_eq!(exit_code(check(&job, &job_paths)), 11);
+    }
+}
@@ -0,0 +1,695 @@
+use crate::{
+    log_rotation::{LogPolicy, LogRotation, LogSize},
+    notify::{Notify, NotifyOn, Preset},
+    paths::{JobPaths, Paths},
+    retention::Retention,
+    secret::Secret,
+    shell,
+};
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::{fmt, fs, os::unix::fs::PermissionsExt, path::PathBuf, process::Command, str::FromStr};
+
+pub const DEFAULT_JOB: &str = "default";
+/// Seconds `--on-overlap wait` waits for the previous run by default.
+pub const DEFAULT_LOCK_TIMEOUT: u64 = 3600;
+
+/// What triggers a job's backups.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Scheduler {
+    /// A line in the user's crontab
+    #[default]
+    Cron,
+    /// A systemd user .timer/.service pair
+    Systemd,
+}
+
+impl fmt::Display for Scheduler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Scheduler::Cron => write!(f, "cron"),
+            Scheduler::Systemd => write!(f, "systemd"),
+        }
+    }
+}
+
+impl FromStr for Scheduler {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "cron" => Ok(Scheduler::Cron),
+            "systemd" => Ok(Scheduler::Systemd),
+            _ => bail!("Unknown scheduler '{}', expected 'cron' or 'systemd'", s),
+        }
+    }
+}
+
+/// What the scheduler starts for each backup.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Runner {
+    /// `rcloneup run --job <job>`
+    #[default]
+    Binary,
+    /// A generated bash script, as written by older releases
+    Script,
+}
+
+impl fmt::Display for Runner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Runner::Binary => write!(f, "binary"),
+            Runner::Script => write!(f, "script"),
+        }
+    }
+}
+
+impl FromStr for Runner {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "binary" => Ok(Runner::Binary),
+            "script" => Ok(Runner::Script),
+            _ => bail!("Unknown runner '{}', expected 'binary' or 'script'", s),
+        }
+    }
+}
+
+/// What a run does when the previous run of the same job still holds its lock.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Overlap {
+    /// Exit without backing up
+    #[default]
+    Skip,
+    /// Wait up to --lock-timeout seconds for it to finish
+    Wait,
+    /// Stop the previous run, then start
+    Kill,
+}
+
+impl fmt::Display for Overlap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Overlap::Skip => write!(f, "skip"),
+            Overlap::Wait => write!(f, "wait"),
+            Overlap::Kill => write!(f, "kill"),
+        }
+    }
+}
+
+impl FromStr for Overlap {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "skip" => Ok(Overlap::Skip),
+            "wait" => Ok(Overlap::Wait),
+            "kill" => Ok(Overlap::Kill),
+            _ => bail!(
+                "Unknown overlap behaviour '{}', expected 'skip', 'wait' or 'kill'",
+                s
+            ),
+        }
+    }
+}
+
+/// Directory in a job's destination that `Mode::Versioned` moves deleted
+/// and overwritten files into, one timestamped prefix per run.
+pub const ARCHIVE_DIR: &str = ".rcloneup-archive";
+
+/// Format of the archive prefixes below `ARCHIVE_DIR`, in UTC.
+pub const ARCHIVE_TIMESTAMP: &str = "%Y-%m-%dT%H%M%SZ";
+
+/// How a run copies the source to the remote.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Mode {
+    /// Make the remote match the source, deleting files removed locally
+    #[default]
+    Sync,
+    /// Upload new and changed files, never delete on the remote
+    Copy,
+    /// Sync, but keep deleted and overwritten files in a timestamped archive
+    Versioned,
+}
+
+impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Mode::Sync => write!(f, "sync"),
+            Mode::Copy => write!(f, "copy"),
+            Mode::Versioned => write!(f, "versioned"),
+        }
+    }
+}
+
+impl FromStr for Mode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "sync" => Ok(Mode::Sync),
+            "copy" => Ok(Mode::Copy),
+            "versioned" => Ok(Mode::Versioned),
+            _ => bail!(
+                "Unknown mode '{}', expected 'sync', 'copy' or 'versioned'",
+                s
+            ),
+        }
+    }
+}
+
+/// How credentials are stored in rclone.conf.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Credentials {
+    /// In plain text, readable only by the owner (0600)
+    #[default]
+    Plain,
+    /// Password fields in rclone's reversible `rclone obscure` format
+    Obscured,
+    /// The whole file encrypted with RCLONE_CONFIG_PASS
+    Encrypted,
+}
+
+impl fmt::Display for Credentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Credentials::Plain => write!(f, "plain"),
+            Credentials::Obscured => write!(f, "obscured"),
+            Credentials::Encrypted => write!(f, "encrypted"),
+        }
+    }
+}
+
+impl FromStr for Credentials {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "plain" => Ok(Credentials::Plain),
+            "obscured" => Ok(Credentials::Obscured),
+            "encrypted" => Ok(Credentials::Encrypted),
+            _ => bail!(
+                "Unknown credentials mode '{}', expected 'plain', 'obscured' or 'encrypted'",
+                s
+            ),
+        }
+    }
+}
+
+/// Where unattended runs get the password of an encrypted rclone.conf.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PasswordSource {
+    /// A file only its owner can read.
+    File(PathBuf),
+    /// A shell command that prints the password, e.g. `pass show rclone`.
+    Command(String),
+}
+
+impl PasswordSource {
+    pub fn read(&self) -> Result<Secret> {
+        let raw = match self {
+            PasswordSource::File(path) => {
+                let mode = fs::metadata(path)
+                    .with_context(|| {
+                        format!("Cannot read config password file {}", path.display())
+                    })?
+                    .permissions()
+                    .mode();
+                if mode & 0o077 != 0 {
+                    bail!(
+                        "Config password file {} must only be accessible by its owner (chmod 600), has mode {:o}",
+                        path.display(),
+                        mode & 0o777
+                    );
+                }
+                fs::read_to_string(path).with_context(|| {
+                    format!("Failed to read config password file {}", path.display())
+                })?
+            }
+            PasswordSource::Command(command) => {
+                let output = Command::new("sh")
+                    .arg("-c")
+                    .arg(command)
+                    .output()
+                    .context("Failed to run config password command")?;
+                if !output.status.success() {
+                    bail!("Config password command failed with {}", output.status);
+                }
+                String::from_utf8(output.stdout)
+                    .context("Config password command printed invalid UTF-8")?
+            }
+        };
+        Ok(Secret::new(raw.trim_end_matches(['\n', '\r'])))
+    }
+
+    /// Shell lines that load the password into RCLONE_CONFIG_PASS.
+    pub fn script_lines(&self) -> String {
+        let fetch = match self {
+            PasswordSource::File(path) => {
+                format!("cat -- {}", shell::quote(&path.to_string_lossy()))
+            }
+            // The command is shell text supplied by the user, so it is run as-is.
+            PasswordSource::Command(command) => command.clone(),
+        };
+        format!(
+            "RCLONE_CONFIG_PASS=\"$({})\" || exit 1\nexport RCLONE_CONFIG_PASS\n",
+            fetch
+        )
+    }
+}
+
+/// What a job backs up and when, as recorded by `setup`.
+#[derive(Debug, Clone)]
+pub struct Job {
+    pub name: String,
+    pub source: String,
+    pub remote: String,
+    pub bucket: String,
+    pub cron: String,
+    pub scheduler: Scheduler,
+    pub mode: Mode,
+    /// Which archives `Mode::Versioned` keeps.
+    pub retention: Retention,
+    pub runner: Runner,
+    pub on_overlap: Overlap,
+    /// Seconds to wait for the previous run with `Overlap::Wait`.
+    pub lock_timeout: u64,
+    pub require_mountpoint: bool,
+    /// File below the source that must exist before a run.
+    pub sentinel: Option<String>,
+    /// Largest drop in the source's file count since the last run, in percent.
+    pub max_drop: Option<u32>,
+    /// Passed to rclone as `--max-delete`.
+    pub max_delete: Option<u64>,
+    /// How `backup.log` is kept in check.
+    pub logs: LogPolicy,
+    /// Where runs report their outcome, if anywhere.
+    pub notify: Option<Notify>,
+    pub credentials: Credentials,
+    pub config_password: Option<PasswordSource>,
+    /// The crypt remote layered over `remote:bucket`, if backups are encrypted.
+    pub crypt_remote: Option<String>,
+    /// `RcloneConfig::fingerprint` of the job's sections as setup wrote them.
+    pub config_fingerprint: Option<String>,
+}
+
+/// A job as stored in its `job.toml`. Values are kept exactly, so a
+/// source path with surrounding spaces reads back unchanged.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Record {
+    source: String,
+    remote: String,
+    bucket: String,
+    cron: String,
+    scheduler: Scheduler,
+    mode: Mode,
+    runner: Runner,
+    on_overlap: Overlap,
+    lock_timeout: u64,
+    require_mountpoint: bool,
+    sentinel: Option<String>,
+    max_drop: Option<u32>,
+    max_delete: Option<u64>,
+    keep_last: Option<u32>,
+    keep_daily: Option<u32>,
+    keep_weekly: Option<u32>,
+    keep_monthly: Option<u32>,
+    max_age: Option<u32>,
+    log_rotation: LogRotation,
+    log_max_size: LogSize,
+    log_max_age: Option<u32>,
+    log_keep: u32,
+    notify_url: Option<String>,
+    notify_preset: Option<Preset>,
+    notify_on: Option<NotifyOn>,
+    notify_template: Option<String>,
+    credentials: Credentials,
+    config_password_file: Option<PathBuf>,
+    config_password_command: Option<String>,
+    crypt_remote: Option<String>,
+    config_fingerprint: Option<String>,
+}
+
+impl Job {
+    pub fn load(paths: &Paths, name: &str) -> Result<Self> {
+        let path = paths.job(name).record;
+        if !path.exists() {
+            bail!(
+                "Unknown job '{}' (run 'rcloneup setup --job {}' first)",
+                name,
+                name
+            );
+        }
+        let text = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read job record {}", path.display()))?;
+        let record: Record = toml::from_str(&text)
+            .with_context(|| format!("Invalid job record {}", path.display()))?;
+        Ok(Self {
+            name: name.to_string(),
+            source: record.source,
+            remote: record.remote,
+            bucket: record.bucket,
+            cron: record.cron,
+            scheduler: record.scheduler,
+            mode: record.mode,
+            retention: Retention {
+                keep_last: record.keep_last,
+                keep_daily: record.keep_daily,
+                keep_weekly: record.keep_weekly,
+                keep_monthly: record.keep_monthly,
+                max_age: record.max_age,
+            },
+            runner: record.runner,
+            on_overlap: record.on_overlap,
+            lock_timeout: record.lock_timeout,
+            require_mountpoint: record.require_mountpoint,
+            sentinel: record.sentinel,
+            max_drop: record.max_drop,
+            max_delete: record.max_delete,
+            logs: LogPolicy {
+                rotation: record.log_rotation,
+                max_size: record.log_max_size,
+                max_age: record.log_max_age,
+                keep: record.log_keep,
+            },
+            notify: record.notify_url.map(|url| Notify {
+                url: Secret::new(url),
+                preset: record.notify_preset.unwrap_or_default(),
+                on: record.notify_on.unwrap_or_default(),
+                template: record.notify_template,
+            }),
+            credentials: record.credentials,
+            config_password: match (record.config_password_file, record.config_password_command) {
+                (Some(file), _) => Some(PasswordSource::File(file)),
+                (None, Some(command)) => Some(PasswordSource::Command(command)),
+                (None, None) => None,
+            },
+            crypt_remote: record.crypt_remote,
+            config_fingerprint: record.config_fingerprint,
+        })
+    }
+
+    /// Loads every job that has a record, sorted by name.
+    pub fn list(paths: &Paths) -> Result<Vec<Self>> {
+        if !paths.jobs_dir.exists() {
+            return Ok(Vec::new());
+        }
+        let mut jobs = Vec::new();
+        for entry in fs::read_dir(&paths.jobs_dir)
+            .with_context(|| format!("Failed to read {}", paths.jobs_dir.display()))?
+        {
+            let name = entry?.file_name().to_string_lossy().into_owned();
+            if paths.job(&name).record.exists() {
+                jobs.push(Self::load(paths, &name)?);
+            }
+        }
+        jobs.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(jobs)
+    }
+
+    /// Where backups are written: the crypt remote if there is one,
+    /// otherwise `remote:bucket`.
+    pub fn destination(&self) -> String {
+        match &self.crypt_remote {
+            Some(crypt_remote) => format!("{}:", crypt_remote),
+            None => format!("{}:{}", self.remote, self.bucket),
+        }
+    }
+
+    /// What the scheduler starts for this job.
+    pub fn scheduled_command(&self, job_paths: &JobPaths) -> Result<Vec<String>> {
+        Ok(match self.runner {
+            Runner::Binary => {
+                let exe = std::env::current_exe().context("Failed to find the rcloneup binary")?;
+                vec![
+                    exe.to_string_lossy().into_owned(),
+                    "run".to_string(),
+                    "--job".to_string(),
+                    self.name.clone(),
+                ]
+            }
+            Runner::Script => vec![job_paths.backup_script.to_string_lossy().into_owned()],
+        })
+    }
+
+    /// The rclone.conf sections the job's backups go through.
+    pub fn config_sections(&self) -> Vec<&str> {
+        let mut sections = vec![self.remote.as_str()];
+        sections.extend(self.crypt_remote.as_deref());
+        sections
+    }
+
+    /// `path` below the job's destination.
+    pub fn destination_path(&self, path: &str) -> String {
+        let destination = self.destination();
+        if destination.ends_with(':') || destination.ends_with('/') {
+            format!("{}{}", destination, path)
+        } else {
+            format!("{}/{}", destination, path)
+        }
+    }
+
+    /// The archive prefix `Mode::Versioned` moves files into for a run
+    /// started at `now`.
+    pub fn archive_dir(&self, now: DateTime<Utc>) -> String {
+        self.destination_path(&format!(
+            "{}/{}",
+            ARCHIVE_DIR,
+            now.format(ARCHIVE_TIMESTAMP)
+        ))
+    }
+
+    /// The contents of the job's `job.toml`.
+    pub fn render_record(&self) -> Result<String> {
+        let (config_password_file, config_password_command) = match &self.config_password {
+            Some(PasswordSource::File(path)) => (Some(path.clone()), None),
+            Some(PasswordSource::Command(command)) => (None, Some(command.clone())),
+            None => (None, None),
+        };
+        let notify = self.notify.as_ref();
+        let record = Record {
+            source: self.source.clone(),
+            remote: self.remote.clone(),
+            bucket: self.bucket.clone(),
+            cron: self.cron.clone(),
+            scheduler: self.scheduler,
+            mode: self.mode,
+            runner: self.runner,
+            on_overlap: self.on_overlap,
+            lock_timeout: self.lock_timeout,
+            require_mountpoint: self.require_mountpoint,
+            sentinel: self.sentinel.clone(),
+            max_drop: self.max_drop,
+            max_delete: self.max_delete,
+            keep_last: self.retention.keep_last,
+            keep_daily: self.retention.keep_daily,
+            keep_weekly: self.retention.keep_weekly,
+            keep_monthly: self.retention.keep_monthly,
+            max_age: self.retention.max_age,
+            log_rotation: self.logs.rotation,
+            log_max_size: self.logs.max_size,
+            log_max_age: self.logs.max_age,
+            log_keep: self.logs.keep,
+            notify_url: notify.map(|n| n.url.expose().to_string()),
+            notify_preset: notify.map(|n| n.preset),
+            notify_on: notify.map(|n| n.on),
+            notify_template: notify.and_then(|n| n.template.clone()),
+            credentials: self.credentials,
+            config_password_file,
+            config_password_command,
+            crypt_remote: self.crypt_remote.clone(),
+            config_fingerprint: self.config_fingerprint.clone(),
+        };
+        Ok(format!(
+            "# Written by rcloneup setup.\n{}",
+            toml::to_string(&record).context("Failed to serialize the job record")?
+        ))
+    }
+}
+
+/// Reads the rclone.conf password using the first of `jobs` that has a
+/// password source; every job sharing an encrypted config records one.
+pub fn config_password(jobs: &[Job]) -> Result<Option<Secret>> {
+    jobs.iter()
+        .find_map(|job| job.config_password.as_ref())
+        .map(PasswordSource::read)
+        .transpose()
+}
+
+/// Name of the crypt remote `setup --encrypt` creates for `job`.
+pub fn crypt_remote_name(job: &str) -> String {
+    format!("rcloneup-{}-crypt", job)
+}
+
+pub fn validate_name(name: &str) -> Result<()> {
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        bail!(
+            "Job name must be non-empty and contain only letters, digits, '-' or '_', got '{}'",
+            name
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, TempDir, HOSTILE_PATHS};
+    use chrono::TimeZone;
+
+    fn save(paths: &Paths, job: &Job) {
+        let job_paths = paths.job(&job.name);
+        fs::create_dir_all(&job_paths.dir).unwrap();
+        fs::write(&job_paths.record, job.render_record().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn record_keeps_hostile_values_exactly() {
+        let dir = TempDir::new();
+        let paths = dir.paths();
+        for source in HOSTILE_PATHS {
+            let mut job = testing::job("hostile", source);
+            job.bucket = format!(" {} ", source);
+            job.sentinel = Some(format!("{}\t", source));
+            job.config_password = Some(PasswordSource::File(PathBuf::from(source)));
+            save(&paths, &job);
+            let loaded = Job::load(&paths, "hostile").unwrap();
+            assert_eq!(loaded.source, source);
+            assert_eq!(loaded.bucket, job.bucket);
+            assert_eq!(loaded.sentinel, job.sentinel);
+            assert_eq!(loaded.config_password, job.config_password);
+        }
+    }
+
+    #[test]
+    fn record_round_trips_every_setting() {
+        let dir = TempDir::new();
+        let paths = dir.paths();
+        let job = Job {
+            scheduler: Scheduler::Systemd,
+            mode: Mode::Versioned,
+            retention: Retention {
+                keep_last: Some(3),
+                keep_daily: Some(7),
+                keep_weekly: None,
+                keep_monthly: Some(12),
+                max_age: Some(400),
+            },
+            runner: Runner::Script,
+            on_overlap: Overlap::Wait,
+            lock_timeout: 90,
+            require_mountpoint: true,
+            max_drop: Some(50),
+            max_delete: Some(1000),
+            logs: LogPolicy {
+                rotation: LogRotation::Logrotate,
+                max_size: LogSize(512 << 10),
+                max_age: Some(30),
+                keep: 2,
+            },
+            notify: Some(Notify {
+                url: Secret::new("https://hooks.example/T0/B0/xyz"),
+                preset: Preset::Slack,
+                on: NotifyOn::Recovery,
+                template: Some("{\"text\": \"{{message}}\"}\n".to_string()),
+            }),
+            credentials: Credentials::Encrypted,
+            config_password: Some(PasswordSource::Command(
+                "pass show rclone | head -1".to_string(),
+            )),
+            crypt_remote: Some(crypt_remote_name("photos")),
+            config_fingerprint: Some("abc123".to_string()),
+            ..testing::job("photos", "/home/user/Pictures")
+        };
+        save(&paths, &job);
+        let loaded = Job::load(&paths, "photos").unwrap();
+        assert_eq!(
+            loaded.render_record().unwrap(),
+            job.render_record().unwrap()
+        );
+        assert_eq!(loaded.retention, job.retention);
+        assert_eq!(loaded.logs, job.logs);
+        let notify = loaded.notify.unwrap();
+        assert_eq!(notify.url.expose(), "https://hooks.example/T0/B0/xyz");
+        assert_eq!(notify.template, job.notify.unwrap().template);
+    }
+
+    #[test]
+    fn record_rejects_missing_and_unknown_keys() {
+        let dir = TempDir::new();
+        let paths = dir.paths();
+        let job = testing::job("broken", "/data");
+        save(&paths, &job);
+        let record = &paths.job("broken").record;
+        let text = job.render_record().unwrap();
+
+        fs::write(record, text.replace("cron = ", "schedule = ")).unwrap();
+        let error = format!("{:#}", Job::load(&paths, "broken").unwrap_err());
+        assert!(error.contains("schedule"), "{}", error);
+
+        fs::write(record, text.replace("runner = \"binary\"\n", "")).unwrap();
+        let error = format!("{:#}", Job::load(&paths, "broken").unwrap_err());
+        assert!(error.contains("runner"), "{}", error);
+
+        assert!(Job::load(&paths, "missing").is_err());
+    }
+
+    #[test]
+    fn list_loads_jobs_by_name() {
+        let dir = TempDir::new();
+        let paths = dir.paths();
+        assert!(Job::list(&paths).unwrap().is_empty());
+        for name in ["web", "db", "photos"] {
+            save(&paths, &testing::job(name, "/data"));
+        }
+        // A directory without a record is not a job.
+        fs::create_dir_all(paths.jobs_dir.join("stray")).unwrap();
+        let names: Vec<String> = Job::list(&paths)
+            .unwrap()
+            .into_iter()
+            .map(|job| job.name)
+            .collect();
+        assert_eq!(names, ["db", "photos", "web"]);
+    }
+
+    #[test]
+    fn validate_name_allows_only_safe_characters() {
+        for name in ["default", "db-dumps", "photos_2"] {
+            assert!(validate_name(name).is_ok(), "{}", name);
+        }
+        for name in ["", "a b", "../x", "a/b", "é", "x;y"] {
+            assert!(validate_name(name).is_err(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn archives_live_below_the_destination() {
+        let now = Utc.with_ymd_and_hms(2026, 3, 9, 7, 5, 0).unwrap();
+        let job = testing::job("web", "/data");
+        assert_eq!(
+            job.archive_dir(now),
+            "minio:backups/.rcloneup-archive/2026-03-09T070500Z"
+        );
+        let job = Job {
+            crypt_remote: Some("minio-crypt".to_string()),
+            ..job
+        };
+        assert_eq!(
+            job.archive_dir(now),
+            "minio-crypt:.rcloneup-archive/2026-03-09T070500Z"
+        );
+    }
+}
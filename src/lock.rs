@@ -0,0 +1,291 @@
+use crate::job::Overlap;
+use anyhow::{bail, Context, Result};
+use std::{
+    fs::{File, OpenOptions, TryLockError},
+    io::{Read, Seek, SeekFrom, Write},
+    os::unix::fs::OpenOptionsExt,
+    path::Path,
+    thread,
+    time::{Duration, Instant},
+};
+
+/// How long `Overlap::Kill` waits after SIGTERM before sending SIGKILL.
+const KILL_GRACE: Duration = Duration::from_secs(30);
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// An flock held on a job's lock file for the length of a run. The file
+/// records the PIDs of rcloneup and of the rclone it started, so that a
+/// later run can tell who holds the lock and `--on-overlap kill` knows
+/// what to stop. It is emptied again when the run ends.
+#[derive(Debug)]
+pub struct RunLock {
+    file: File,
+}
+
+impl RunLock {
+    /// Takes the lock at `path`, dealing with a run that still holds it
+    /// according to `overlap`. Returns `None` if this run should be skipped.
+    pub fn acquire(
+        path: &Path,
+        overlap: Overlap,
+        timeout: Duration,
+        verbose: bool,
+    ) -> Result<Option<Self>> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .mode(0o600)
+            .open(path)
+            .with_context(|| format!("Failed to open lock file {}", path.display()))?;
+
+        let mut stopped = false;
+        if !try_lock(&file, path)? {
+            let holders = read_pids(&mut file)?;
+            let running: Vec<i32> = holders.iter().copied().filter(|p| is_alive(*p)).collect();
+            let describe = match holders.first() {
+                Some(pid) if running.is_empty() => format!(
+                    "by a process other than the recorded PID {}, which is no longer running",
+                    pid
+                ),
+                Some(pid) => format!("by PID {}", pid),
+                None => "by an unknown process".to_string(),
+            };
+
+            match overlap {
+                Overlap::Skip => {
+                    println!(
+                        "The previous run still holds {} ({}), skipping this run.",
+                        path.display(),
+                        describe
+                    );
+                    return Ok(None);
+                }
+                Overlap::Wait => {
+                    println!(
+                        "Waiting up to {}s for the previous run to finish (lock held {}).",
+                        timeout.as_secs(),
+                        describe
+                    );
+                    if !wait_for_lock(&file, path, timeout)? {
+                        bail!(
+                            "Timed out after {}s waiting for the previous run to release {}",
+                            timeout.as_secs(),
+                            path.display()
+                        );
+                    }
+                }
+                Overlap::Kill => {
+                    if running.is_empty() {
+                        bail!("{} is held {}; cannot stop it", path.display(), describe);
+                    }
+                    println!("Stopping the previous run (PIDs {:?}).", running);
+                    signal(&running, libc::SIGTERM)?;
+                    stopped = true;
+                    if !wait_for_lock(&file, path, KILL_GRACE)? {
+                        println!(
+                            "Previous run did not stop within {}s, sending SIGKILL.",
+                            KILL_GRACE.as_secs()
+                        );
+                        let running: Vec<i32> =
+                            running.into_iter().filter(|p| is_alive(*p)).collect();
+                        signal(&running, libc::SIGKILL)?;
+                        if !wait_for_lock(&file, path, timeout)? {
+                            bail!("Previous run still holds {} after SIGKILL", path.display());
+                        }
+                    }
+                }
+            }
+        }
+
+        // A clean run empties the file, so PIDs left in it mean the last
+        // run died without releasing the lock properly.
+        let stale = read_pids(&mut file)?;
+        if let Some(pid) = stale.first().filter(|_| !stopped) {
+            println!(
+                "Found stale lock from PID {}; the previous run did not finish cleanly.",
+                pid
+            );
+        } else if verbose {
+            println!("Acquired lock {}", path.display());
+        }
+
+        let mut lock = Self { file };
+        lock.record(&[std::process::id()])?;
+        Ok(Some(lock))
+    }
+
+    /// Records the PID of the rclone process started for this run.
+    pub fn record_child(&mut self, child: u32) -> Result<()> {
+        self.record(&[std::process::id(), child])
+    }
+
+    fn record(&mut self, pids: &[u32]) -> Result<()> {
+        let text: Vec<String> = pids.iter().map(u32::to_string).collect();
+        self.file.set_len(0)?;
+        self.file.seek(SeekFrom::Start(0))?;
+        writeln!(self.file, "{}", text.join(" "))?;
+        self.file.flush()?;
+        Ok(())
+    }
+}
+
+impl Drop for RunLock {
+    fn drop(&mut self) {
+        // The flock itself is released when the file is closed.
+        let _ = self.file.set_len(0);
+    }
+}
+
+fn try_lock(file: &File, path: &Path) -> Result<bool> {
+    match file.try_lock() {
+        Ok(()) => Ok(true),
+        Err(TryLockError::WouldBlock) => Ok(false),
+        Err(TryLockError::Error(e)) => {
+            Err(e).with_context(|| format!("Failed to lock {}", path.display()))
+        }
+    }
+}
+
+fn wait_for_lock(file: &File, path: &Path, timeout: Duration) -> Result<bool> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        if try_lock(file, path)? {
+            return Ok(true);
+        }
+        if Instant::now() >= deadline {
+            return Ok(false);
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+fn read_pids(file: &mut File) -> Result<Vec<i32>> {
+    let mut text = String::new();
+    file.seek(SeekFrom::Start(0))?;
+    file.read_to_string(&mut text)?;
+    Ok(text
+        .split_whitespace()
+        .filter_map(|pid| pid.parse().ok())
+        .filter(|pid| *pid > 0)
+        .collect())
+}
+
+fn is_alive(pid: i32) -> bool {
+    // Signal 0 only checks whether the process exists; EPERM means it
+    // exists but belongs to someone else.
+    let found = unsafe { libc::kill(pid, 0) } == 0;
+    found || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+fn signal(pids: &[i32], signal: i32) -> Result<()> {
+    for pid in pids {
+        if unsafe { libc::kill(*pid, signal) } != 0 {
+            let err = std::io::Error::last_os_error();
+            if err.raw_os_error() != Some(libc::ESRCH) {
+                return Err(err).with_context(|| format!("Failed to signal PID {}", pid));
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+    use std::fs;
+
+    const POLICIES: [Overlap; 3] = [Overlap::Skip, Overlap::Wait, Overlap::Kill];
+
+    fn acquire(path: &Path, overlap: Overlap, timeout: Duration) -> Result<Option<RunLock>> {
+        RunLock::acquire(path, overlap, timeout, false)
+    }
+
+    #[test]
+    fn skip_leaves_a_held_lock_alone() {
+        let dir = TempDir::new();
+        let path = dir.path().join("run.lock");
+        let held = acquire(&path, Overlap::Skip, Duration::ZERO)
+            .unwrap()
+            .unwrap();
+        assert!(acquire(&path, Overlap::Skip, Duration::ZERO)
+            .unwrap()
+            .is_none());
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            format!("{}\n", std::process::id())
+        );
+
+        drop(held);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "");
+        assert!(acquire(&path, Overlap::Skip, Duration::ZERO)
+            .unwrap()
+            .is_some());
+    }
+
+    #[test]
+    fn wait_takes_the_lock_once_released_or_times_out() {
+        let dir = TempDir::new();
+        let path = dir.path().join("run.lock");
+        let held = acquire(&path, Overlap::Skip, Duration::ZERO)
+            .unwrap()
+            .unwrap();
+        let err = acquire(&path, Overlap::Wait, Duration::ZERO).unwrap_err();
+        assert!(err.to_string().starts_with("Timed out after 0s"));
+
+        let release = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(200));
+            drop(held);
+        });
+        let lock = acquire(&path, Overlap::Wait, Duration::from_secs(10)).unwrap();
+        assert!(lock.is_some());
+        release.join().unwrap();
+    }
+
+    #[test]
+    fn kill_refuses_when_no_recorded_process_is_running() {
+        let dir = TempDir::new();
+        let path = dir.path().join("run.lock");
+        let mut held = acquire(&path, Overlap::Skip, Duration::ZERO)
+            .unwrap()
+            .unwrap();
+        // i32::MAX is above any pid_max, so it never names a live process.
+        held.file.set_len(0).unwrap();
+        held.file.seek(SeekFrom::Start(0)).unwrap();
+        writeln!(held.file, "{}", i32::MAX).unwrap();
+        let err = acquire(&path, Overlap::Kill, Duration::ZERO).unwrap_err();
+        assert!(err.to_string().contains("cannot stop it"), "{}", err);
+    }
+
+    #[test]
+    fn stale_lock_is_taken_over_by_every_policy() {
+        let dir = TempDir::new();
+        let path = dir.path().join("run.lock");
+        for overlap in POLICIES {
+            // A run that died keeps its PIDs in the file but not the flock.
+            fs::write(&path, format!("{} {}\n", i32::MAX, i32::MAX - 1)).unwrap();
+            let lock = acquire(&path, overlap, Duration::ZERO).unwrap();
+            assert!(lock.is_some(), "{} did not take the lock", overlap);
+            assert_eq!(
+                fs::read_to_string(&path).unwrap(),
+                format!("{}\n", std::process::id())
+            );
+        }
+    }
+
+    #[test]
+    fn record_child_adds_the_rclone_pid() {
+        let dir = TempDir::new();
+        let path = dir.path().join("run.lock");
+        let mut lock = acquire(&path, Overlap::Skip, Duration::ZERO)
+            .unwrap()
+            .unwrap();
+        lock.record_child(4242).unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            format!("{} 4242\n", std::process::id())
+        );
+    }
+}
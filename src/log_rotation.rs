@@ -0,0 +1,399 @@
+use crate::paths::JobPaths;
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Duration, FixedOffset, Utc};
+use clap::ValueEnum;
+use flate2::{write::GzEncoder, Compression};
+use serde::{Deserialize, Serialize};
+use std::{
+    ffi::OsString,
+    fmt,
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader},
+    os::unix::fs::OpenOptionsExt,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+/// Rotate a job's log once it reaches this size, unless set otherwise.
+pub const DEFAULT_LOG_MAX_SIZE: LogSize = LogSize(10 << 20);
+
+/// How many rotated logs a job keeps, unless set otherwise.
+pub const DEFAULT_LOG_KEEP: u32 = 5;
+
+/// Who keeps a job's `backup.log` from growing without bound.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogRotation {
+    /// `rcloneup run` rotates and compresses the log itself
+    #[default]
+    Rotate,
+    /// Write a logrotate config for the job and leave rotation to logrotate
+    Logrotate,
+    /// Let the log grow
+    Off,
+}
+
+impl fmt::Display for LogRotation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LogRotation::Rotate => write!(f, "rotate"),
+            LogRotation::Logrotate => write!(f, "logrotate"),
+            LogRotation::Off => write!(f, "off"),
+        }
+    }
+}
+
+impl FromStr for LogRotation {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "rotate" => Ok(LogRotation::Rotate),
+            "logrotate" => Ok(LogRotation::Logrotate),
+            "off" => Ok(LogRotation::Off),
+            _ => bail!(
+                "Unknown log rotation '{}', expected 'rotate', 'logrotate' or 'off'",
+                s
+            ),
+        }
+    }
+}
+
+/// A size in bytes, written like logrotate's: `512k`, `10M` or `1G`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct LogSize(pub u64);
+
+impl fmt::Display for LogSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (unit, scale) in [("G", 1u64 << 30), ("M", 1 << 20), ("k", 1 << 10)] {
+            if self.0 >= scale && self.0.is_multiple_of(scale) {
+                return write!(f, "{}{}", self.0 / scale, unit);
+            }
+        }
+        write!(f, "{}", self.0)
+    }
+}
+
+impl FromStr for LogSize {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (number, scale) = match s.char_indices().last() {
+            Some((i, 'k' | 'K')) => (&s[..i], 1u64 << 10),
+            Some((i, 'm' | 'M')) => (&s[..i], 1 << 20),
+            Some((i, 'g' | 'G')) => (&s[..i], 1 << 30),
+            _ => (s, 1),
+        };
+        match number
+            .parse::<u64>()
+            .ok()
+            .and_then(|n| n.checked_mul(scale))
+        {
+            Some(bytes) if bytes > 0 => Ok(LogSize(bytes)),
+            _ => bail!("Invalid log size '{}', expected e.g. 512k, 10M or 1G", s),
+        }
+    }
+}
+
+impl From<LogSize> for String {
+    fn from(size: LogSize) -> Self {
+        size.to_string()
+    }
+}
+
+impl TryFrom<String> for LogSize {
+    type Error = anyhow::Error;
+
+    fn try_from(s: String) -> Result<Self> {
+        s.parse()
+    }
+}
+
+/// When a job's log is rotated and how many old logs are kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LogPolicy {
+    pub rotation: LogRotation,
+    /// Rotate once the log is at least this big.
+    pub max_size: LogSize,
+    /// Rotate once the log's first line is older than this many days.
+    pub max_age: Option<u32>,
+    /// Rotated logs to keep, as `backup.log.1.gz` (newest) and up.
+    pub keep: u32,
+}
+
+impl LogPolicy {
+    /// Short description for `status`, e.g. "rotate at 10M or 30 days, keep 5".
+    pub fn describe(&self) -> String {
+        if self.rotation == LogRotation::Off {
+            return "not rotated".to_string();
+        }
+        let mut text = format!("{} at {}", self.rotation, self.max_size);
+        if let Some(days) = self.max_age {
+            text.push_str(&format!(" or {} days", days));
+        }
+        text.push_str(&format!(", keep {}", self.keep));
+        text
+    }
+
+    /// A logrotate config with the same limits. logrotate only rotates
+    /// by age on a fixed interval, so `max_age` picks the nearest one.
+    pub fn logrotate_config(&self, log_file: &Path) -> String {
+        let mut rules = Vec::new();
+        match self.max_age {
+            Some(days) => {
+                rules.push(
+                    match days {
+                        0..=1 => "daily",
+                        2..=7 => "weekly",
+                        _ => "monthly",
+                    }
+                    .to_string(),
+                );
+                rules.push(format!("maxsize {}", self.max_size));
+            }
+            None => rules.push(format!("size {}", self.max_size)),
+        }
+        rules.push(format!("rotate {}", self.keep));
+        // Every run opens the log anew, so a plain rename is enough.
+        rules.extend(["compress", "missingok", "notifempty", "nocreate"].map(String::from));
+        format!(
+            "# Created by rcloneup setup.\n\"{}\" {{\n{}}}\n",
+            log_file.display(),
+            rules
+                .iter()
+                .map(|rule| format!("    {}\n", rule))
+                .collect::<String>()
+        )
+    }
+
+    /// Whether the log at `log_file` is due for rotation.
+    fn due(&self, log_file: &Path) -> Result<bool> {
+        let Ok(metadata) = fs::metadata(log_file) else {
+            return Ok(false);
+        };
+        if metadata.len() == 0 {
+            return Ok(false);
+        }
+        if metadata.len() >= self.max_size.0 {
+            return Ok(true);
+        }
+        let Some(days) = self.max_age else {
+            return Ok(false);
+        };
+        let started = match first_line_time(log_file)? {
+            Some(time) => time,
+            None => match metadata.created() {
+                Ok(created) => created.into(),
+                Err(_) => return Ok(false),
+            },
+        };
+        Ok(Utc::now() - started > Duration::days(i64::from(days)))
+    }
+}
+
+/// The time of the first JSON line in `log_file`, which is when the log
+/// was started, or `None` for logs written before rcloneup used JSON.
+fn first_line_time(log_file: &Path) -> Result<Option<DateTime<Utc>>> {
+    #[derive(Deserialize)]
+    struct Line {
+        time: DateTime<FixedOffset>,
+    }
+
+    let file =
+        File::open(log_file).with_context(|| format!("Failed to read {}", log_file.display()))?;
+    let mut first = String::new();
+    BufReader::new(file).read_line(&mut first)?;
+    Ok(serde_json::from_str::<Line>(&first)
+        .ok()
+        .map(|line| line.time.with_timezone(&Utc)))
+}
+
+/// The `n`th rotated log of `log_file`, e.g. `backup.log.1.gz`.
+fn rotated_log(log_file: &Path, n: u32) -> PathBuf {
+    let mut name = OsString::from(log_file.as_os_str());
+    name.push(format!(".{}.gz", n));
+    PathBuf::from(name)
+}
+
+/// Every rotated log the job has, whatever its current `keep`.
+pub fn rotated_logs(job_paths: &JobPaths) -> Vec<PathBuf> {
+    (1..)
+        .map(|n| rotated_log(&job_paths.log_file, n))
+        .take_while(|path| path.exists())
+        .collect()
+}
+
+/// Compresses the job's log into `backup.log.1.gz`, shifting older ones
+/// up and dropping those beyond `policy.keep`, if the log is due.
+/// Returns whether it rotated. Runs under the job's run lock, so no rclone
+/// is writing the log meanwhile.
+pub fn rotate_if_due(job_paths: &JobPaths, policy: &LogPolicy, verbose: bool) -> Result<bool> {
+    if policy.rotation != LogRotation::Rotate || !policy.due(&job_paths.log_file)? {
+        return Ok(false);
+    }
+    let log_file = &job_paths.log_file;
+
+    // Compress first, so a failure leaves the log and older ones intact.
+    let tmp = rotated_log(log_file, 1).with_extension("gz.tmp");
+    let mut input =
+        File::open(log_file).with_context(|| format!("Failed to read {}", log_file.display()))?;
+    let output = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(&tmp)
+        .with_context(|| format!("Failed to write {}", tmp.display()))?;
+    let mut encoder = GzEncoder::new(output, Compression::default());
+    io::copy(&mut input, &mut encoder)
+        .with_context(|| format!("Failed to compress {}", log_file.display()))?;
+    encoder.finish()?.sync_all()?;
+
+    for old in rotated_logs(job_paths)
+        .into_iter()
+        .skip(policy.keep.saturating_sub(1) as usize)
+    {
+        fs::remove_file(&old).with_context(|| format!("Failed to remove {}", old.display()))?;
+    }
+    for n in (1..policy.keep).rev() {
+        let from = rotated_log(log_file, n);
+        if from.exists() {
+            fs::rename(&from, rotated_log(log_file, n + 1))
+                .with_context(|| format!("Failed to rename {}", from.display()))?;
+        }
+    }
+    let rotated = rotated_log(log_file, 1);
+    fs::rename(&tmp, &rotated)
+        .with_context(|| format!("Failed to replace {}", rotated.display()))?;
+    fs::remove_file(log_file)
+        .with_context(|| format!("Failed to remove {}", log_file.display()))?;
+    if verbose {
+        println!("Rotated {} to {}", log_file.display(), rotated.display());
+    }
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+    use flate2::read::GzDecoder;
+    use std::io::Read;
+
+    fn policy(max_size: u64, max_age: Option<u32>, keep: u32) -> LogPolicy {
+        LogPolicy {
+            rotation: LogRotation::Rotate,
+            max_size: LogSize(max_size),
+            max_age,
+            keep,
+        }
+    }
+
+    fn gunzip(path: &Path) -> String {
+        let mut text = String::new();
+        GzDecoder::new(File::open(path).unwrap())
+            .read_to_string(&mut text)
+            .unwrap();
+        text
+    }
+
+    #[test]
+    fn log_size_parses_and_displays_like_logrotate() {
+        for (text, bytes, shown) in [
+            ("512k", 512 << 10, "512k"),
+            ("512K", 512 << 10, "512k"),
+            ("10M", 10 << 20, "10M"),
+            ("1g", 1 << 30, "1G"),
+            ("2048", 2048, "2k"),
+            ("1536", 1536, "1536"),
+            ("1024M", 1 << 30, "1G"),
+        ] {
+            let size: LogSize = text.parse().unwrap();
+            assert_eq!(size, LogSize(bytes), "{}", text);
+            assert_eq!(size.to_string(), shown);
+            assert_eq!(shown.parse::<LogSize>().unwrap(), size);
+        }
+        for text in ["", "0", "0M", "k", "-1", "1.5M", "10X", "99999999999G"] {
+            assert!(text.parse::<LogSize>().is_err(), "accepted {:?}", text);
+        }
+    }
+
+    #[test]
+    fn rotation_shifts_old_logs_and_keeps_only_keep() {
+        let dir = TempDir::new();
+        let job_paths = dir.paths().job("default");
+        fs::create_dir_all(&job_paths.dir).unwrap();
+        let keep_3 = policy(10, None, 3);
+
+        fs::write(&job_paths.log_file, "short").unwrap();
+        assert!(!rotate_if_due(&job_paths, &keep_3, false).unwrap());
+
+        for run in 1..=5 {
+            fs::write(&job_paths.log_file, format!("log of run {}\n", run)).unwrap();
+            assert!(rotate_if_due(&job_paths, &keep_3, false).unwrap());
+            assert!(!job_paths.log_file.exists());
+        }
+        let rotated = rotated_logs(&job_paths);
+        assert_eq!(rotated.len(), 3);
+        for (n, path) in rotated.iter().enumerate() {
+            assert_eq!(*path, rotated_log(&job_paths.log_file, n as u32 + 1));
+            assert_eq!(gunzip(path), format!("log of run {}\n", 5 - n));
+        }
+        assert!(!rotated_log(&job_paths.log_file, 1)
+            .with_extension("gz.tmp")
+            .exists());
+
+        // Lowering keep drops the surplus on the next rotation.
+        fs::write(&job_paths.log_file, "log of run 6\n").unwrap();
+        assert!(rotate_if_due(&job_paths, &policy(10, None, 1), false).unwrap());
+        assert_eq!(rotated_logs(&job_paths).len(), 1);
+        assert_eq!(
+            gunzip(&rotated_log(&job_paths.log_file, 1)),
+            "log of run 6\n"
+        );
+    }
+
+    #[test]
+    fn rotation_by_age_reads_the_first_line() {
+        let dir = TempDir::new();
+        let job_paths = dir.paths().job("default");
+        fs::create_dir_all(&job_paths.dir).unwrap();
+        let line = |days: i64| {
+            format!(
+                "{{\"time\":\"{}\",\"level\":\"info\",\"msg\":\"x\"}}\n",
+                (Utc::now() - Duration::days(days)).to_rfc3339()
+            )
+        };
+
+        fs::write(&job_paths.log_file, line(3)).unwrap();
+        assert!(!rotate_if_due(&job_paths, &policy(1 << 20, Some(7), 5), false).unwrap());
+        fs::write(&job_paths.log_file, line(8) + &line(0)).unwrap();
+        assert!(!rotate_if_due(&job_paths, &policy(1 << 20, None, 5), false).unwrap());
+        assert!(rotate_if_due(&job_paths, &policy(1 << 20, Some(7), 5), false).unwrap());
+
+        let off = LogPolicy {
+            rotation: LogRotation::Off,
+            ..policy(1, None, 5)
+        };
+        fs::write(&job_paths.log_file, "big enough").unwrap();
+        assert!(!rotate_if_due(&job_paths, &off, false).unwrap());
+    }
+
+    #[test]
+    fn logrotate_config_matches_the_policy() {
+        let log_file = Path::new("/home/u/jobs/my job/backup.log");
+        assert_eq!(
+            policy(10 << 20, None, 5).logrotate_config(log_file),
+            "# Created by rcloneup setup.\n\
+             \"/home/u/jobs/my job/backup.log\" {\n    \
+             size 10M\n    rotate 5\n    compress\n    missingok\n    notifempty\n    nocreate\n}\n"
+        );
+        let config = policy(512 << 10, Some(30), 2).logrotate_config(log_file);
+        assert!(config.contains("\n    monthly\n    maxsize 512k\n    rotate 2\n"));
+        for (days, interval) in [(1, "daily"), (7, "weekly"), (8, "monthly")] {
+            let config = policy(1 << 20, Some(days), 1).logrotate_config(log_file);
+            assert!(config.contains(&format!("    {}\n", interval)));
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use crate::{files::write_if_changed, paths::JobPaths};
+use anyhow::{bail, Context, Result};
+use std::{collections::BTreeSet, fs, path::PathBuf};
+
+/// Everything `setup` created for a job, so that `uninstall` removes
+/// exactly that and leaves alone whatever was there before.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Manifest {
+    /// rclone.conf sections rcloneup created, as opposed to remotes that
+    /// already existed and were only updated.
+    pub config_sections: BTreeSet<String>,
+    pub files: BTreeSet<PathBuf>,
+    pub cron: bool,
+    pub systemd_timer: bool,
+}
+
+impl Manifest {
+    /// Reads the manifest of a job, or `None` if setup has not recorded one.
+    pub fn load(job_paths: &JobPaths) -> Result<Option<Self>> {
+        let path = &job_paths.manifest;
+        if !path.exists() {
+            return Ok(None);
+        }
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read manifest {}", path.display()))?;
+
+        let mut manifest = Manifest::default();
+        // Values are taken as written; paths may start or end with spaces.
+        for line in text.lines() {
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (kind, value) = line.split_once(' ').unwrap_or((line, ""));
+            match kind {
+                "section" => {
+                    manifest.config_sections.insert(value.to_string());
+                }
+                "file" => {
+                    manifest.files.insert(PathBuf::from(value));
+                }
+                "cron" => manifest.cron = true,
+                "systemd-timer" => manifest.systemd_timer = true,
+                _ => bail!("Unknown entry '{}' in manifest {}", line, path.display()),
+            }
+        }
+        Ok(Some(manifest))
+    }
+
+    /// Records the job's record and the files its runs write.
+    pub fn add_state_files(&mut self, job_paths: &JobPaths) {
+        self.files.extend([
+            job_paths.record.clone(),
+            job_paths.log_file.clone(),
+            job_paths.lock_file.clone(),
+            job_paths.file_count.clone(),
+            job_paths.history.clone(),
+            job_paths.history_lock.clone(),
+        ]);
+    }
+
+    pub fn render(&self) -> String {
+        let mut text = String::from(
+            "# Created by rcloneup setup; rcloneup uninstall removes exactly these.\n",
+        );
+        for section in &self.config_sections {
+            text.push_str(&format!("section {}\n", section));
+        }
+        for file in &self.files {
+            text.push_str(&format!("file {}\n", file.display()));
+        }
+        if self.cron {
+            text.push_str("cron\n");
+        }
+        if self.systemd_timer {
+            text.push_str("systemd-timer\n");
+        }
+        text
+    }
+
+    pub fn save(&self, job_paths: &JobPaths, verbose: bool) -> Result<()> {
+        write_if_changed(
+            &job_paths.manifest,
+            self.render().as_bytes(),
+            0o600,
+            verbose,
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{TempDir, HOSTILE_PATHS};
+
+    #[test]
+    fn manifest_round_trips() {
+        let dir = TempDir::new();
+        let job_paths = dir.paths().job("web");
+        fs::create_dir_all(&job_paths.dir).unwrap();
+        assert_eq!(Manifest::load(&job_paths).unwrap(), None);
+
+        let mut manifest = Manifest {
+            config_sections: ["minio".to_string(), "minio-crypt".to_string()].into(),
+            files: HOSTILE_PATHS.iter().map(PathBuf::from).collect(),
+            cron: true,
+            systemd_timer: false,
+        };
+        manifest.save(&job_paths, false).unwrap();
+        assert_eq!(
+            Manifest::load(&job_paths).unwrap().as_ref(),
+            Some(&manifest)
+        );
+
+        manifest.cron = false;
+        manifest.systemd_timer = true;
+        manifest.config_sections.clear();
+        manifest.save(&job_paths, false).unwrap();
+        assert_eq!(Manifest::load(&job_paths).unwrap(), Some(manifest));
+    }
+
+    #[test]
+    fn unknown_entries_are_rejected() {
+        let dir = TempDir::new();
+        let job_paths = dir.paths().job("web");
+        fs::create_dir_all(&job_paths.dir).unwrap();
+        fs::write(&job_paths.manifest, "# comment\n\ncron\nfolder /tmp\n").unwrap();
+        let err = Manifest::load(&job_paths).unwrap_err();
+        assert!(
+            err.to_string().starts_with("Unknown entry 'folder /tmp'"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn state_files_cover_what_runs_write() {
+        let dir = TempDir::new();
+        let job_paths = dir.paths().job("web");
+        let mut manifest = Manifest::default();
+        manifest.add_state_files(&job_paths);
+        for file in [
+            &job_paths.record,
+            &job_paths.log_file,
+            &job_paths.lock_file,
+            &job_paths.file_count,
+            &job_paths.history,
+            &job_paths.history_lock,
+        ] {
+            assert!(manifest.files.contains(file), "{}", file.display());
+        }
+        // The manifest itself is removed last, by uninstall.
+        assert!(!manifest.files.contains(&job_paths.manifest));
+    }
+}
@@ -0,0 +1,101 @@
+use anyhow::{Context, Result};
+use std::path::PathBuf;
+
+/// Locations of every file rcloneup manages.
+#[derive(Debug)]
+pub struct Paths {
+    pub rclone_config_dir: PathBuf,
+    pub rclone_config_file: PathBuf,
+    pub jobs_dir: PathBuf,
+    pub systemd_user_dir: PathBuf,
+    /// Crypt passwords of encrypted jobs; kept on uninstall.
+    pub recovery_dir: PathBuf,
+    /// Single script written by releases before named jobs existed.
+    pub legacy_backup_script: PathBuf,
+}
+
+/// Files belonging to a single named job.
+#[derive(Debug)]
+pub struct JobPaths {
+    pub dir: PathBuf,
+    pub record: PathBuf,
+    /// What setup created for the job, see `Manifest`.
+    pub manifest: PathBuf,
+    pub backup_script: PathBuf,
+    pub log_file: PathBuf,
+    /// Snippet for `LogRotation::Logrotate`, see `LogPolicy::logrotate_config`.
+    pub logrotate_config: PathBuf,
+    pub lock_file: PathBuf,
+    /// Source file count of the last successful run.
+    pub file_count: PathBuf,
+    /// One JSON record per run, see `state::append`.
+    pub history: PathBuf,
+    pub history_lock: PathBuf,
+    pub service_unit: PathBuf,
+    pub timer_unit: PathBuf,
+    pub recovery_file: PathBuf,
+}
+
+impl Paths {
+    pub fn resolve() -> Result<Self> {
+        let home_dir = dirs::home_dir().context("Could not find home directory")?;
+        let config_dir = home_dir.join(".config");
+        let rclone_config_dir = config_dir.join("rclone");
+        let state_dir = dirs::state_dir()
+            .unwrap_or_else(|| home_dir.join(".local").join("state"))
+            .join("rcloneup");
+        Ok(Self {
+            rclone_config_file: rclone_config_dir.join("rclone.conf"),
+            rclone_config_dir,
+            jobs_dir: state_dir.join("jobs"),
+            systemd_user_dir: config_dir.join("systemd").join("user"),
+            recovery_dir: config_dir.join("rcloneup").join("recovery"),
+            legacy_backup_script: home_dir.join("rclone_backup.sh"),
+        })
+    }
+
+    pub fn job(&self, name: &str) -> JobPaths {
+        let dir = self.jobs_dir.join(name);
+        JobPaths {
+            record: dir.join("job.toml"),
+            manifest: dir.join("manifest"),
+            backup_script: dir.join("backup.sh"),
+            log_file: dir.join("backup.log"),
+            logrotate_config: dir.join("logrotate.conf"),
+            lock_file: dir.join("run.lock"),
+            file_count: dir.join("file-count"),
+            history: dir.join("runs.jsonl"),
+            history_lock: dir.join("runs.lock"),
+            service_unit: self
+                .systemd_user_dir
+                .join(format!("rcloneup-{}.service", name)),
+            timer_unit: self
+                .systemd_user_dir
+                .join(format!("rcloneup-{}.timer", name)),
+            recovery_file: self.recovery_dir.join(format!("{}-crypt.txt", name)),
+            dir,
+        }
+    }
+
+    pub fn print(&self) {
+        println!("Rclone config dir: {}", self.rclone_config_dir.display());
+        println!("Rclone config file: {}", self.rclone_config_file.display());
+        println!("Jobs dir: {}", self.jobs_dir.display());
+    }
+}
+
+impl JobPaths {
+    pub fn print(&self) {
+        println!("Job dir: {}", self.dir.display());
+        println!("Backup script: {}", self.backup_script.display());
+        println!("Log file: {}", self.log_file.display());
+    }
+
+    pub fn timer_name(&self) -> String {
+        self.timer_unit
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned()
+    }
+}
@@ -0,0 +1,253 @@
+use anyhow::{bail, Result};
+use clap::ValueEnum;
+use serde::Deserialize;
+use std::fmt;
+
+/// The S3-compatible service a remote talks to.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Provider {
+    /// MinIO or another self-hosted server at --endpoint
+    #[default]
+    Minio,
+    /// Amazon S3 (needs --region)
+    Aws,
+    /// Backblaze B2 through its S3 API (needs --region, e.g. us-west-004)
+    B2,
+    /// Wasabi [default region: us-east-1]
+    Wasabi,
+    /// Cloudflare R2 (needs --account-id)
+    R2,
+    /// Ceph Object Gateway (needs --endpoint)
+    Ceph,
+    /// DigitalOcean Spaces (needs --region, e.g. nyc3)
+    #[value(name = "digitalocean")]
+    DigitalOcean,
+    /// Any other S3-compatible service (needs --endpoint)
+    Other,
+}
+
+impl fmt::Display for Provider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Provider::Minio => write!(f, "minio"),
+            Provider::Aws => write!(f, "aws"),
+            Provider::B2 => write!(f, "b2"),
+            Provider::Wasabi => write!(f, "wasabi"),
+            Provider::R2 => write!(f, "r2"),
+            Provider::Ceph => write!(f, "ceph"),
+            Provider::DigitalOcean => write!(f, "digitalocean"),
+            Provider::Other => write!(f, "other"),
+        }
+    }
+}
+
+impl Provider {
+    /// The value of rclone's `provider` option.
+    pub fn rclone_name(&self) -> &'static str {
+        match self {
+            Provider::Minio => "Minio",
+            Provider::Aws => "AWS",
+            // rclone has no B2 entry in its S3 provider list.
+            Provider::B2 => "Other",
+            Provider::Wasabi => "Wasabi",
+            Provider::R2 => "Cloudflare",
+            Provider::Ceph => "Ceph",
+            Provider::DigitalOcean => "DigitalOcean",
+            Provider::Other => "Other",
+        }
+    }
+
+    pub fn default_region(&self) -> Option<&'static str> {
+        match self {
+            Provider::Wasabi => Some("us-east-1"),
+            Provider::R2 => Some("auto"),
+            _ => None,
+        }
+    }
+
+    /// The endpoint to use when none is given. AWS needs none, while Ceph
+    /// and generic servers have no endpoint we could guess.
+    pub fn default_endpoint(
+        &self,
+        region: Option<&str>,
+        account_id: Option<&str>,
+    ) -> Option<String> {
+        match (self, region, account_id) {
+            (Provider::Minio, _, _) => Some("http://localhost:9000".to_string()),
+            (Provider::B2, Some(region), _) => {
+                Some(format!("https://s3.{}.backblazeb2.com", region))
+            }
+            (Provider::Wasabi, Some(region), _) => {
+                Some(format!("https://s3.{}.wasabisys.com", region))
+            }
+            (Provider::R2, _, Some(account_id)) => {
+                Some(format!("https://{}.r2.cloudflarestorage.com", account_id))
+            }
+            (Provider::DigitalOcean, Some(region), _) => {
+                Some(format!("https://{}.digitaloceanspaces.com", region))
+            }
+            _ => None,
+        }
+    }
+
+    /// Checks that the provider-specific settings it needs are present.
+    pub fn validate(
+        &self,
+        region: Option<&str>,
+        account_id: Option<&str>,
+        location_constraint: Option<&str>,
+        endpoint: Option<&str>,
+    ) -> Result<()> {
+        let needs_region = matches!(self, Provider::Aws | Provider::B2 | Provider::DigitalOcean);
+        if needs_region && region.is_none() {
+            bail!("Provider '{}' needs --region", self);
+        }
+        if let Some(region) = region {
+            if region.is_empty()
+                || !region
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+            {
+                bail!(
+                    "Region must contain only lowercase letters, digits and '-', got '{}'",
+                    region
+                );
+            }
+        }
+
+        match (self, account_id) {
+            (Provider::R2, None) => bail!("Provider 'r2' needs --account-id"),
+            (Provider::R2, Some(id)) => {
+                if id.len() != 32 || !id.chars().all(|c| c.is_ascii_hexdigit()) {
+                    bail!(
+                        "Cloudflare account ID must be 32 hexadecimal characters, got '{}'",
+                        id
+                    );
+                }
+            }
+            (_, Some(_)) => bail!("--account-id only applies to provider 'r2'"),
+            (_, None) => {}
+        }
+
+        if location_constraint.is_some() && *self != Provider::Aws {
+            bail!("--location-constraint only applies to provider 'aws'");
+        }
+
+        if endpoint.is_none() && *self != Provider::Aws {
+            bail!("Provider '{}' needs --endpoint", self);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    const ACCOUNT: &str = "0123456789abcdef0123456789abcdef";
+
+    /// The rclone.conf entries setup writes for `args`.
+    fn entries(args: &[&str]) -> Result<Vec<(&'static str, String)>> {
+        let base = ["--source", "/", "--access-key", "a", "--secret-key", "b"];
+        let args: Vec<&str> = base.iter().chain(args).copied().collect();
+        let job = testing::setup_jobs(&args, "")?.remove(0);
+        job.remote_setup.validate()?;
+        Ok(job.remote_setup.entries())
+    }
+
+    #[test]
+    fn providers_fill_in_endpoint_and_region() {
+        let cases = [
+            (&[][..], "Minio", None, Some("http://localhost:9000")),
+            (
+                &["--provider", "aws", "--region", "eu-west-1"],
+                "AWS",
+                Some("eu-west-1"),
+                None,
+            ),
+            (
+                &["--provider", "b2", "--region", "us-west-004"],
+                "Other",
+                Some("us-west-004"),
+                Some("https://s3.us-west-004.backblazeb2.com"),
+            ),
+            (
+                &["--provider", "wasabi"],
+                "Wasabi",
+                Some("us-east-1"),
+                Some("https://s3.us-east-1.wasabisys.com"),
+            ),
+            (
+                &["--provider", "r2", "--account-id", ACCOUNT],
+                "Cloudflare",
+                Some("auto"),
+                Some("https://0123456789abcdef0123456789abcdef.r2.cloudflarestorage.com"),
+            ),
+            (
+                &["--provider", "ceph", "--endpoint", "http://rgw.lan:7480"],
+                "Ceph",
+                None,
+                Some("http://rgw.lan:7480"),
+            ),
+        ];
+        for (args, provider, region, endpoint) in cases {
+            let entries = entries(args).unwrap();
+            let get = |key| {
+                entries
+                    .iter()
+                    .find(|(k, _)| *k == key)
+                    .map(|(_, v)| v.as_str())
+            };
+            assert_eq!(get("type"), Some("s3"), "{:?}", args);
+            assert_eq!(get("provider"), Some(provider), "{:?}", args);
+            assert_eq!(get("region"), region, "{:?}", args);
+            assert_eq!(get("endpoint"), endpoint, "{:?}", args);
+        }
+    }
+
+    #[test]
+    fn explicit_endpoint_wins_over_the_default() {
+        let entries = entries(&[
+            "--provider",
+            "wasabi",
+            "--endpoint",
+            "https://s3.eu.wasabisys.com",
+        ])
+        .unwrap();
+        assert!(entries.contains(&("endpoint", "https://s3.eu.wasabisys.com".to_string())));
+    }
+
+    #[test]
+    fn provider_settings_are_validated() {
+        for (args, message) in [
+            (&["--provider", "aws"][..], "Provider 'aws' needs --region"),
+            (
+                &["--provider", "digitalocean"],
+                "Provider 'digitalocean' needs --region",
+            ),
+            (&["--provider", "r2"], "Provider 'r2' needs --account-id"),
+            (
+                &["--provider", "r2", "--account-id", "abc"],
+                "must be 32 hexadecimal",
+            ),
+            (
+                &["--account-id", ACCOUNT],
+                "--account-id only applies to provider 'r2'",
+            ),
+            (
+                &["--location-constraint", "EU"],
+                "--location-constraint only applies",
+            ),
+            (&["--provider", "ceph"], "Provider 'ceph' needs --endpoint"),
+            (
+                &["--provider", "aws", "--region", "EU West"],
+                "Region must contain only",
+            ),
+        ] {
+            let err = entries(args).unwrap_err().to_string();
+            assert!(err.contains(message), "{:?}: {}", args, err);
+        }
+    }
+}
@@ -0,0 +1,488 @@
+use crate::{files::write_if_changed, rclone_crypt, secret::Secret};
+use anyhow::{bail, Context, Result};
+use sha2::{Digest, Sha256};
+use std::{fmt, fs, path::Path};
+
+/// Parts of key names that hold credentials in rclone's backends, such as
+/// `secret_access_key`, `pass`, `token` or `client_secret`.
+const SENSITIVE_KEY_PARTS: [&str; 5] = ["secret", "pass", "token", "key", "credentials"];
+
+fn is_sensitive(key: &str) -> bool {
+    SENSITIVE_KEY_PARTS.iter().any(|part| key.contains(part))
+}
+
+/// One line of an rclone config file. The original text is kept so that
+/// lines we do not touch are written back byte-for-byte.
+#[derive(Debug, Clone)]
+enum Line {
+    Section {
+        name: String,
+        raw: String,
+    },
+    Entry {
+        key: String,
+        value: String,
+        raw: String,
+    },
+    Other(String),
+}
+
+impl Line {
+    fn parse(raw: &str) -> Line {
+        let trimmed = raw.trim();
+        if trimmed.starts_with('[') && trimmed.ends_with(']') && trimmed.len() >= 2 {
+            return Line::Section {
+                name: trimmed[1..trimmed.len() - 1].trim().to_string(),
+                raw: raw.to_string(),
+            };
+        }
+        if trimmed.is_empty() || trimmed.starts_with('#') || trimmed.starts_with(';') {
+            return Line::Other(raw.to_string());
+        }
+        match trimmed.split_once('=') {
+            Some((key, value)) => Line::Entry {
+                key: key.trim().to_string(),
+                value: value.trim().to_string(),
+                raw: raw.to_string(),
+            },
+            None => Line::Other(raw.to_string()),
+        }
+    }
+
+    fn entry(key: &str, value: &str) -> Line {
+        Line::Entry {
+            key: key.to_string(),
+            value: value.to_string(),
+            raw: format!("{} = {}", key, value),
+        }
+    }
+
+    fn raw(&self) -> &str {
+        match self {
+            Line::Section { raw, .. } | Line::Entry { raw, .. } => raw,
+            Line::Other(raw) => raw,
+        }
+    }
+}
+
+/// A key that was added, updated or removed while merging a section.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeyChange {
+    Added(String),
+    Updated(String),
+    Removed(String),
+}
+
+impl fmt::Display for KeyChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyChange::Added(key) => write!(f, "added {}", key),
+            KeyChange::Updated(key) => write!(f, "updated {}", key),
+            KeyChange::Removed(key) => write!(f, "removed {}", key),
+        }
+    }
+}
+
+/// An rclone.conf file that can be edited one section at a time.
+#[derive(Debug, Clone, Default)]
+pub struct RcloneConfig {
+    lines: Vec<Line>,
+    /// Rendered contents and encryption as loaded, to tell whether `save`
+    /// has anything to do.
+    loaded: Option<(String, bool)>,
+}
+
+impl RcloneConfig {
+    /// Reads the config at `path`, treating a missing file as empty. An
+    /// encrypted config is decrypted with `password`.
+    pub fn load(path: &Path, password: Option<&Secret>) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read rclone config {}", path.display()))?;
+        let encrypted = rclone_crypt::is_encrypted(&text);
+        let text = match (encrypted, password) {
+            (false, _) => text,
+            (true, Some(password)) => rclone_crypt::decrypt_config(&text, password)
+                .with_context(|| format!("Failed to decrypt {}", path.display()))?,
+            (true, None) => bail!(
+                "rclone config {} is encrypted; use --credentials encrypted with --config-password-file or --config-password-command",
+                path.display()
+            ),
+        };
+        let mut config = Self::parse(&text);
+        config.loaded = Some((config.render(), encrypted));
+        Ok(config)
+    }
+
+    /// True if the config at `path` exists and is encrypted.
+    pub fn is_encrypted_file(path: &Path) -> Result<bool> {
+        if !path.exists() {
+            return Ok(false);
+        }
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read rclone config {}", path.display()))?;
+        Ok(rclone_crypt::is_encrypted(&text))
+    }
+
+    pub fn parse(text: &str) -> Self {
+        Self {
+            lines: text.lines().map(Line::parse).collect(),
+            loaded: None,
+        }
+    }
+
+    /// Writes the config to `path`, encrypted if `password` is given.
+    /// Encryption uses a fresh nonce every time, so the file is left alone
+    /// when neither its contents nor its encryption would change.
+    pub fn save(&self, path: &Path, password: Option<&Secret>, verbose: bool) -> Result<bool> {
+        let plaintext = self.render();
+        if path.exists() && self.loaded.as_ref() == Some(&(plaintext.clone(), password.is_some())) {
+            if verbose {
+                println!("File up to date: {}", path.display());
+            }
+            return Ok(false);
+        }
+        let content = match password {
+            Some(password) => rclone_crypt::encrypt_config(&plaintext, password)?,
+            None => plaintext,
+        };
+        write_if_changed(path, content.as_bytes(), 0o600, verbose)
+    }
+
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        for line in &self.lines {
+            out.push_str(line.raw());
+            out.push('\n');
+        }
+        out
+    }
+
+    /// Like `render`, but with every credential masked for printing.
+    pub fn render_redacted(&self) -> String {
+        let mut out = String::new();
+        for line in &self.lines {
+            match line {
+                Line::Entry { key, value, .. } if is_sensitive(key) => {
+                    out.push_str(&format!("{} = {}", key, Secret::new(value.as_str())));
+                }
+                _ => out.push_str(line.raw()),
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    /// Returns the index range `(header, end)` of the first section called `name`.
+    fn section_range(&self, name: &str) -> Option<(usize, usize)> {
+        let start = self
+            .lines
+            .iter()
+            .position(|l| matches!(l, Line::Section { name: n, .. } if n == name))?;
+        let end = self.lines[start + 1..]
+            .iter()
+            .position(|l| matches!(l, Line::Section { .. }))
+            .map(|i| start + 1 + i)
+            .unwrap_or(self.lines.len());
+        Some((start, end))
+    }
+
+    pub fn get(&self, section: &str, key: &str) -> Option<&str> {
+        let (start, end) = self.section_range(section)?;
+        self.lines[start + 1..end].iter().find_map(|l| match l {
+            Line::Entry { key: k, value, .. } if k == key => Some(value.as_str()),
+            _ => None,
+        })
+    }
+
+    /// A digest of the entries of `sections`, to tell later whether they
+    /// were edited. Missing sections count as empty.
+    pub fn fingerprint(&self, sections: &[&str]) -> String {
+        let mut hasher = Sha256::new();
+        for name in sections {
+            hasher.update(format!("[{}]\n", name));
+            if let Some((start, end)) = self.section_range(name) {
+                for line in &self.lines[start + 1..end] {
+                    if let Line::Entry { key, value, .. } = line {
+                        hasher.update(format!("{}={}\n", key, value));
+                    }
+                }
+            }
+        }
+        hasher
+            .finalize()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    }
+
+    pub fn has_section(&self, name: &str) -> bool {
+        self.section_range(name).is_some()
+    }
+
+    /// Drops section `name` through its last entry. Comments and blank
+    /// lines after that are left, since they usually introduce the next
+    /// section; a blank line separating the section from the one before
+    /// goes with it.
+    pub fn remove_section(&mut self, name: &str) -> bool {
+        let Some((mut start, end)) = self.section_range(name) else {
+            return false;
+        };
+        let end = self.lines[start + 1..end]
+            .iter()
+            .rposition(|l| matches!(l, Line::Entry { .. }))
+            .map_or(start + 1, |i| start + 2 + i);
+        if start > 0 && self.lines[start - 1].raw().trim().is_empty() {
+            start -= 1;
+        }
+        self.lines.drain(start..end);
+        true
+    }
+
+    /// Sets `entries` in section `name`, in place if the section already
+    /// exists or appended at the end of the file otherwise. Keys in `owned`
+    /// that `entries` lacks are removed; other keys, comments and every
+    /// other section are left alone.
+    pub fn upsert_section(
+        &mut self,
+        name: &str,
+        entries: &[(&str, String)],
+        owned: &[&str],
+    ) -> Vec<KeyChange> {
+        let mut changes = Vec::new();
+
+        let Some((start, end)) = self.section_range(name) else {
+            if self
+                .lines
+                .last()
+                .is_some_and(|l| !l.raw().trim().is_empty())
+            {
+                self.lines.push(Line::Other(String::new()));
+            }
+            self.lines.push(Line::Section {
+                name: name.to_string(),
+                raw: format!("[{}]", name),
+            });
+            for (key, value) in entries {
+                self.lines.push(Line::entry(key, value));
+                changes.push(KeyChange::Added(key.to_string()));
+            }
+            return changes;
+        };
+
+        let mut section = Vec::with_capacity(end - start);
+        let mut seen: Vec<&str> = Vec::new();
+        let mut last_entry = 0;
+        for line in self.lines.drain(start + 1..end) {
+            let Line::Entry { key, value, .. } = &line else {
+                section.push(line);
+                continue;
+            };
+            let wanted = entries.iter().find(|(k, _)| k == key);
+            match wanted {
+                Some((k, new_value)) if !seen.contains(k) => {
+                    seen.push(*k);
+                    if value != new_value {
+                        changes.push(KeyChange::Updated(key.clone()));
+                        section.push(Line::entry(key, new_value));
+                    } else {
+                        section.push(line);
+                    }
+                    last_entry = section.len();
+                }
+                None if !owned.contains(&key.as_str()) => {
+                    section.push(line);
+                    last_entry = section.len();
+                }
+                _ => changes.push(KeyChange::Removed(key.clone())),
+            }
+        }
+
+        let missing: Vec<Line> = entries
+            .iter()
+            .filter(|(k, _)| !seen.contains(k))
+            .map(|(k, v)| {
+                changes.push(KeyChange::Added(k.to_string()));
+                Line::entry(k, v)
+            })
+            .collect();
+        section.splice(last_entry..last_entry, missing);
+
+        self.lines.splice(start + 1..start + 1, section);
+        changes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OWNED: &[&str] = &["type", "provider", "endpoint", "region"];
+
+    const CONF: &str = "\
+# My remotes
+[minio]
+type = s3
+; talks to the lab box
+provider = Minio
+endpoint = http://old:9000
+foo = bar
+
+[gdrive]
+type = drive
+token = {\"a\":1}
+";
+
+    fn entries(pairs: &[(&'static str, &str)]) -> Vec<(&'static str, String)> {
+        pairs.iter().map(|(k, v)| (*k, v.to_string())).collect()
+    }
+
+    #[test]
+    fn untouched_config_renders_byte_for_byte() {
+        let text = "[a]\n  key=value  \n\n# note\n[b]\nx = 1\n";
+        assert_eq!(RcloneConfig::parse(text).render(), text);
+    }
+
+    #[test]
+    fn upsert_keeps_comments_user_keys_and_other_sections() {
+        let mut conf = RcloneConfig::parse(CONF);
+        let changes = conf.upsert_section(
+            "minio",
+            &entries(&[("type", "s3"), ("provider", "AWS"), ("region", "eu-west-1")]),
+            OWNED,
+        );
+        assert_eq!(
+            changes,
+            [
+                KeyChange::Updated("provider".to_string()),
+                KeyChange::Removed("endpoint".to_string()),
+                KeyChange::Added("region".to_string()),
+            ]
+        );
+        assert_eq!(
+            conf.render(),
+            "\
+# My remotes
+[minio]
+type = s3
+; talks to the lab box
+provider = AWS
+foo = bar
+region = eu-west-1
+
+[gdrive]
+type = drive
+token = {\"a\":1}
+"
+        );
+    }
+
+    #[test]
+    fn upsert_changes_nothing_when_values_match() {
+        let mut conf = RcloneConfig::parse(CONF);
+        let changes = conf.upsert_section(
+            "minio",
+            &entries(&[
+                ("type", "s3"),
+                ("provider", "Minio"),
+                ("endpoint", "http://old:9000"),
+            ]),
+            OWNED,
+        );
+        assert!(changes.is_empty());
+        assert_eq!(conf.render(), CONF);
+    }
+
+    #[test]
+    fn upsert_drops_duplicates_of_set_keys() {
+        let mut conf = RcloneConfig::parse("[r]\ntype = s3\ntype = sftp\n");
+        let changes = conf.upsert_section("r", &entries(&[("type", "s3")]), &[]);
+        assert_eq!(changes, [KeyChange::Removed("type".to_string())]);
+        assert_eq!(conf.render(), "[r]\ntype = s3\n");
+    }
+
+    #[test]
+    fn upsert_appends_new_section_after_a_blank_line() {
+        let mut conf = RcloneConfig::parse("[a]\nx = 1");
+        let changes = conf.upsert_section("b", &entries(&[("type", "local")]), OWNED);
+        assert_eq!(changes, [KeyChange::Added("type".to_string())]);
+        assert_eq!(conf.render(), "[a]\nx = 1\n\n[b]\ntype = local\n");
+        assert_eq!(conf.get("b", "type"), Some("local"));
+    }
+
+    #[test]
+    fn remove_section_leaves_neighbours() {
+        let mut conf = RcloneConfig::parse(CONF);
+        assert!(conf.remove_section("minio"));
+        assert!(!conf.remove_section("minio"));
+        assert_eq!(
+            conf.render(),
+            "# My remotes\n\n[gdrive]\ntype = drive\ntoken = {\"a\":1}\n"
+        );
+    }
+
+    #[test]
+    fn remove_section_keeps_comments_above_the_next_section() {
+        let text = "\
+[a]
+x = 1
+
+[rcloneup-job-crypt]
+type = crypt
+# rclone writes no comments, but users do
+password = secret
+
+# Work laptop, do not delete
+; synced from dotfiles
+[b]
+y = 2
+";
+        let mut conf = RcloneConfig::parse(text);
+        assert!(conf.remove_section("rcloneup-job-crypt"));
+        assert_eq!(
+            conf.render(),
+            "\
+[a]
+x = 1
+
+# Work laptop, do not delete
+; synced from dotfiles
+[b]
+y = 2
+"
+        );
+    }
+
+    #[test]
+    fn remove_last_section_leaves_no_trailing_blank_line() {
+        let mut conf = RcloneConfig::parse("[a]\nx = 1\n\n[b]\ny = 2\n");
+        assert!(conf.remove_section("b"));
+        assert_eq!(conf.render(), "[a]\nx = 1\n");
+    }
+
+    #[test]
+    fn redacted_render_masks_credentials() {
+        let conf = RcloneConfig::parse("[s]\naccess_key_id = AKIA\nsecret_access_key = hunter2\n");
+        let text = conf.render_redacted();
+        assert!(
+            !text.contains("AKIA") && !text.contains("hunter2"),
+            "{}",
+            text
+        );
+    }
+
+    #[test]
+    fn redacted_render_keeps_everything_but_credential_values() {
+        let text =
+            "# note\n[nas]\ntype = sftp\nhost = nas.lan\npass = obscured1\nkey_file = /k\n\n\
+                    [cloud]\ntype = webdav\nbearer_token = tok\npassword2 = obscured2\n";
+        let redacted = RcloneConfig::parse(text).render_redacted();
+        assert_eq!(
+            redacted,
+            "# note\n[nas]\ntype = sftp\nhost = nas.lan\npass = ********\nkey_file = ********\n\n\
+             [cloud]\ntype = webdav\nbearer_token = ********\npassword2 = ********\n"
+        );
+    }
+}
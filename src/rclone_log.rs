@@ -0,0 +1,255 @@
+use crate::backup::RunStats;
+use serde::Deserialize;
+
+/// How many of a run's rclone errors its history record keeps.
+const ERROR_SAMPLE: usize = 5;
+
+/// One line of rclone's `--use-json-log` output. Only the fields rcloneup
+/// reads; rclone adds more, such as the source location and object type.
+#[derive(Debug, Deserialize)]
+struct LogLine {
+    #[serde(default)]
+    level: String,
+    #[serde(default)]
+    msg: String,
+    object: Option<String>,
+    stats: Option<RunStats>,
+}
+
+/// Something rclone reported while it ran.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    /// A file was copied to the destination.
+    Transferred { path: String },
+    /// A file was deleted from the destination.
+    Deleted { path: String },
+    Error {
+        path: Option<String>,
+        message: String,
+    },
+    /// rclone gave up on an attempt and started the whole run again.
+    Retry { message: String },
+    /// A stats summary; the last one holds the run's totals.
+    Stats(RunStats),
+}
+
+impl Event {
+    /// Turns a JSON log line into an event, or `None` for lines rcloneup
+    /// does not track and lines that are not JSON at all, such as the
+    /// messages guards write to the same log.
+    pub fn parse(line: &str) -> Option<Self> {
+        let line: LogLine = serde_json::from_str(line.trim()).ok()?;
+        if let Some(stats) = line.stats {
+            return Some(Event::Stats(stats));
+        }
+        let msg = line.msg.trim();
+        if msg.starts_with("Attempt ") && msg.contains(" failed with ") {
+            return Some(Event::Retry {
+                message: msg.to_string(),
+            });
+        }
+        match line.level.as_str() {
+            "error" | "critical" => Some(Event::Error {
+                path: line.object,
+                message: msg.to_string(),
+            }),
+            _ => {
+                let path = line.object?;
+                if msg.contains("Copied (") {
+                    Some(Event::Transferred { path })
+                } else if msg == "Deleted" {
+                    Some(Event::Deleted { path })
+                } else {
+                    None
+                }
+            }
+        }
+    }
+}
+
+/// The events in a JSON log, in order.
+pub fn parse(log: &str) -> Vec<Event> {
+    log.lines().filter_map(Event::parse).collect()
+}
+
+/// What a run's log says about it.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Summary {
+    pub stats: RunStats,
+    /// The first few errors, as `path: message` when rclone named a file.
+    pub errors: Vec<String>,
+}
+
+impl Summary {
+    /// Sums up `events`. The totals come from the last stats summary; if
+    /// rclone died before writing one, they are counted from the events.
+    pub fn from_events(events: &[Event]) -> Self {
+        let mut summary = Summary::default();
+        let mut counted = RunStats::default();
+        let mut final_stats = None;
+        for event in events {
+            match event {
+                Event::Transferred { .. } => counted.transfers += 1,
+                Event::Deleted { .. } => counted.deletes += 1,
+                Event::Error { path, message } => {
+                    counted.errors += 1;
+                    if summary.errors.len() < ERROR_SAMPLE {
+                        summary.errors.push(match path {
+                            Some(path) => format!("{}: {}", path, message),
+                            None => message.clone(),
+                        });
+                    }
+                }
+                Event::Retry { .. } => counted.retries += 1,
+                Event::Stats(stats) => final_stats = Some(*stats),
+            }
+        }
+        summary.stats = final_stats.unwrap_or(counted);
+        summary.stats.retries = counted.retries;
+        summary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COPIED: &str = r#"{"level":"info","msg":"Copied (new)","object":"docs/a.txt","objectType":"*local.Object","source":"operations/copy.go:368","time":"2026-10-17T03:00:01.5+02:00"}"#;
+    const REPLACED: &str = r#"{"level":"info","msg":"Copied (replaced existing)","object":"b.txt","time":"2026-10-17T03:00:01+02:00"}"#;
+    const DELETED: &str =
+        r#"{"level":"info","msg":"Deleted","object":"old.txt","time":"2026-10-17T03:00:02+02:00"}"#;
+    const FAILED: &str = r#"{"level":"error","msg":"Failed to copy: permission denied","object":"c.txt","time":"2026-10-17T03:00:02+02:00"}"#;
+    const RETRY: &str = r#"{"level":"error","msg":"Attempt 1/3 failed with 1 errors and: permission denied","time":"2026-10-17T03:00:03+02:00"}"#;
+    const EARLY_STATS: &str = r#"{"level":"info","msg":"1 MiB / 2 MiB","stats":{"bytes":1048576,"checks":3,"deletes":0,"errors":0,"transfers":1,"speed":1000.5,"elapsedTime":60},"time":"2026-10-17T03:01:00+02:00"}"#;
+    const FINAL_STATS: &str = r#"{"level":"info","msg":"2 MiB / 2 MiB","stats":{"bytes":2097152,"checks":7,"deletes":1,"errors":1,"transfers":2,"speed":1000.5,"elapsedTime":90},"time":"2026-10-17T03:01:30+02:00"}"#;
+
+    fn stats(bytes: u64, checks: u64, transfers: u64, deletes: u64, errors: u64) -> RunStats {
+        RunStats {
+            bytes,
+            checks,
+            transfers,
+            deletes,
+            errors,
+            retries: 0,
+        }
+    }
+
+    #[test]
+    fn parses_each_kind_of_line() {
+        assert_eq!(
+            Event::parse(COPIED),
+            Some(Event::Transferred {
+                path: "docs/a.txt".to_string()
+            })
+        );
+        assert_eq!(
+            Event::parse(REPLACED),
+            Some(Event::Transferred {
+                path: "b.txt".to_string()
+            })
+        );
+        assert_eq!(
+            Event::parse(DELETED),
+            Some(Event::Deleted {
+                path: "old.txt".to_string()
+            })
+        );
+        assert_eq!(
+            Event::parse(FAILED),
+            Some(Event::Error {
+                path: Some("c.txt".to_string()),
+                message: "Failed to copy: permission denied".to_string()
+            })
+        );
+        assert_eq!(
+            Event::parse(RETRY),
+            Some(Event::Retry {
+                message: "Attempt 1/3 failed with 1 errors and: permission denied".to_string()
+            })
+        );
+        assert_eq!(
+            Event::parse(FINAL_STATS),
+            Some(Event::Stats(stats(2097152, 7, 2, 1, 1)))
+        );
+    }
+
+    #[test]
+    fn ignores_untracked_and_foreign_lines() {
+        for line in [
+            "",
+            "The previous run is still going, skipping this run.",
+            "2026/10/17 03:00:00 INFO  : a.txt: Copied (new)",
+            r#"{"level":"info","msg":"Copied (new)"}"#,
+            r#"{"level":"notice","msg":"Skipped copy as --dry-run is set","object":"a.txt"}"#,
+            r#"{"level":"info","msg":"Deleted directory","object":"gone"}"#,
+        ] {
+            assert_eq!(Event::parse(line), None, "{}", line);
+        }
+        // Errors without a file are still errors.
+        assert_eq!(
+            Event::parse(r#"{"level":"critical","msg":"Fatal error: no space"}"#),
+            Some(Event::Error {
+                path: None,
+                message: "Fatal error: no space".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn totals_come_from_the_last_stats_line() {
+        let log = [
+            "Starting backup",
+            COPIED,
+            EARLY_STATS,
+            FAILED,
+            RETRY,
+            REPLACED,
+            DELETED,
+            FINAL_STATS,
+        ]
+        .join("\n");
+        let summary = Summary::from_events(&parse(&log));
+        assert_eq!(
+            summary.stats,
+            RunStats {
+                retries: 1,
+                ..stats(2097152, 7, 2, 1, 1)
+            }
+        );
+        assert_eq!(summary.errors, ["c.txt: Failed to copy: permission denied"]);
+    }
+
+    #[test]
+    fn totals_are_counted_without_a_stats_line() {
+        // rclone was killed before it wrote its summary.
+        let log = [COPIED, REPLACED, DELETED, FAILED, RETRY, FAILED].join("\n");
+        let summary = Summary::from_events(&parse(&log));
+        assert_eq!(
+            summary.stats,
+            RunStats {
+                retries: 1,
+                ..stats(0, 0, 2, 1, 2)
+            }
+        );
+        assert_eq!(summary.errors.len(), 2);
+    }
+
+    #[test]
+    fn keeps_only_the_first_few_errors() {
+        let events: Vec<Event> = (0..ERROR_SAMPLE + 3)
+            .map(|i| Event::Error {
+                path: (i % 2 == 0).then(|| format!("f{}", i)),
+                message: format!("error {}", i),
+            })
+            .collect();
+        let summary = Summary::from_events(&events);
+        assert_eq!(summary.stats.errors, ERROR_SAMPLE as u64 + 3);
+        assert_eq!(summary.errors.len(), ERROR_SAMPLE);
+        assert_eq!(summary.errors[..2], ["f0: error 0", "error 1"]);
+    }
+
+    #[test]
+    fn empty_log_sums_to_nothing() {
+        assert_eq!(Summary::from_events(&parse("")), Summary::default());
+    }
+}
@@ -0,0 +1,374 @@
+use crate::{
+    backup,
+    job::{Job, ARCHIVE_DIR, ARCHIVE_TIMESTAMP},
+};
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Datelike, Duration, NaiveDateTime, Utc};
+use std::{cmp::Reverse, collections::HashSet};
+
+/// rclone's exit code for a directory that does not exist.
+const DIR_NOT_FOUND: i32 = 3;
+
+/// Which of a versioned job's archives to keep. An archive is kept when
+/// any `keep_*` rule selects it (or no rule is set) and it is not older
+/// than `max_age` days.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Retention {
+    pub keep_last: Option<u32>,
+    pub keep_daily: Option<u32>,
+    pub keep_weekly: Option<u32>,
+    pub keep_monthly: Option<u32>,
+    pub max_age: Option<u32>,
+}
+
+impl Retention {
+    pub fn is_empty(&self) -> bool {
+        *self == Retention::default()
+    }
+
+    fn has_keep_rules(&self) -> bool {
+        self.keep_last.is_some()
+            || self.keep_daily.is_some()
+            || self.keep_weekly.is_some()
+            || self.keep_monthly.is_some()
+    }
+
+    /// Short description for `status`, e.g. "last 7, daily 14, max age 90d".
+    pub fn describe(&self) -> String {
+        let mut parts = Vec::new();
+        for (what, count) in [
+            ("last", self.keep_last),
+            ("daily", self.keep_daily),
+            ("weekly", self.keep_weekly),
+            ("monthly", self.keep_monthly),
+        ] {
+            if let Some(count) = count {
+                parts.push(format!("{} {}", what, count));
+            }
+        }
+        if let Some(days) = self.max_age {
+            parts.push(format!("max age {}d", days));
+        }
+        parts.join(", ")
+    }
+
+    /// Returns the archives in `archives` that this policy expires at `now`.
+    fn expired<'a>(&self, archives: &'a [Archive], now: DateTime<Utc>) -> Vec<&'a Archive> {
+        let mut newest_first: Vec<&Archive> = archives.iter().collect();
+        newest_first.sort_by_key(|a| Reverse(a.time));
+
+        let mut keep = HashSet::new();
+        if self.has_keep_rules() {
+            if let Some(count) = self.keep_last {
+                keep.extend(newest_first.iter().take(count as usize).map(|a| &a.name));
+            }
+            select_periods(&newest_first, self.keep_daily, &mut keep, |t| {
+                (t.year(), t.ordinal())
+            });
+            select_periods(&newest_first, self.keep_weekly, &mut keep, |t| {
+                let week = t.iso_week();
+                (week.year(), week.week())
+            });
+            select_periods(&newest_first, self.keep_monthly, &mut keep, |t| {
+                (t.year(), t.month())
+            });
+        } else {
+            keep.extend(newest_first.iter().map(|a| &a.name));
+        }
+
+        newest_first
+            .into_iter()
+            .filter(|archive| {
+                let too_old = self
+                    .max_age
+                    .is_some_and(|days| now - archive.time > Duration::days(i64::from(days)));
+                too_old || !keep.contains(&archive.name)
+            })
+            .collect()
+    }
+}
+
+/// Keeps the newest archive of each of the `count` most recent periods,
+/// as told apart by `period`.
+fn select_periods<'a>(
+    newest_first: &[&'a Archive],
+    count: Option<u32>,
+    keep: &mut HashSet<&'a String>,
+    period: impl Fn(DateTime<Utc>) -> (i32, u32),
+) {
+    let Some(count) = count else {
+        return;
+    };
+    let mut last = None;
+    let mut kept = 0;
+    for archive in newest_first {
+        if kept >= count {
+            break;
+        }
+        let current = period(archive.time);
+        if last != Some(current) {
+            keep.insert(&archive.name);
+            last = Some(current);
+            kept += 1;
+        }
+    }
+}
+
+/// One timestamped prefix below a job's `ARCHIVE_DIR`.
+#[derive(Debug)]
+pub struct Archive {
+    pub name: String,
+    pub time: DateTime<Utc>,
+}
+
+/// Lists `job`'s archives, oldest first. Entries that do not look like
+/// archive timestamps are left alone and not returned.
+pub fn list_archives(job: &Job) -> Result<Vec<Archive>> {
+    let output = backup::rclone_command(job)?
+        .arg("lsf")
+        .arg("--dirs-only")
+        .arg(job.destination_path(ARCHIVE_DIR))
+        .output()
+        .context("Failed to run rclone lsf")?;
+    if output.status.code() == Some(DIR_NOT_FOUND) {
+        return Ok(Vec::new());
+    }
+    if !output.status.success() {
+        bail!(
+            "rclone lsf failed with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    let mut archives: Vec<Archive> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let name = line.trim_end_matches('/');
+            let time = NaiveDateTime::parse_from_str(name, ARCHIVE_TIMESTAMP).ok()?;
+            Some(Archive {
+                name: name.to_string(),
+                time: time.and_utc(),
+            })
+        })
+        .collect();
+    archives.sort_by_key(|a| a.time);
+    Ok(archives)
+}
+
+/// Purges the archives of `job` that its retention policy expires, or
+/// only reports them with `dry_run`. Returns how many were (or would be)
+/// deleted.
+pub fn prune(job: &Job, dry_run: bool, verbose: bool) -> Result<usize> {
+    let archives = list_archives(job)?;
+    let expired = job.retention.expired(&archives, Utc::now());
+    if verbose {
+        println!(
+            "Job '{}' has {} archive(s), {} expired",
+            job.name,
+            archives.len(),
+            expired.len()
+        );
+    }
+
+    for archive in &expired {
+        let path = job.destination_path(&format!("{}/{}", ARCHIVE_DIR, archive.name));
+        if dry_run {
+            println!("(dry-run) Would delete archive {}", path);
+            continue;
+        }
+        let status = backup::rclone_command(job)?
+            .arg("purge")
+            .arg(&path)
+            .status()
+            .context("Failed to run rclone purge")?;
+        if !status.success() {
+            bail!("rclone purge {} failed with {}", path, status);
+        }
+        println!("Deleted archive {}", path);
+    }
+    Ok(expired.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn archives(times: &[&str]) -> Vec<Archive> {
+        times
+            .iter()
+            .map(|time| Archive {
+                name: time.to_string(),
+                time: NaiveDateTime::parse_from_str(time, "%Y-%m-%d %H:%M")
+                    .unwrap()
+                    .and_utc(),
+            })
+            .collect()
+    }
+
+    fn expired(retention: Retention, times: &[&str], now: &str) -> Vec<String> {
+        let now = NaiveDateTime::parse_from_str(now, "%Y-%m-%d %H:%M")
+            .unwrap()
+            .and_utc();
+        let mut names: Vec<String> = retention
+            .expired(&archives(times), now)
+            .into_iter()
+            .map(|a| a.name.clone())
+            .collect();
+        names.sort();
+        names
+    }
+
+    const NOW: &str = "2026-03-10 12:00";
+
+    #[test]
+    fn no_rules_keeps_everything() {
+        let times = ["2020-01-01 00:00", "2026-03-10 11:00"];
+        assert!(expired(Retention::default(), &times, NOW).is_empty());
+    }
+
+    #[test]
+    fn keep_last_keeps_the_newest_whatever_the_input_order() {
+        let retention = Retention {
+            keep_last: Some(2),
+            ..Retention::default()
+        };
+        let times = [
+            "2026-03-10 09:00",
+            "2026-03-10 06:00",
+            "2026-03-10 11:00",
+            "2026-03-10 10:00",
+        ];
+        assert_eq!(
+            expired(retention, &times, NOW),
+            ["2026-03-10 06:00", "2026-03-10 09:00"]
+        );
+    }
+
+    #[test]
+    fn daily_keeps_the_newest_of_each_day_with_an_archive() {
+        let retention = Retention {
+            keep_daily: Some(3),
+            ..Retention::default()
+        };
+        // March 8 has no archive, so the third day kept is March 7.
+        let times = [
+            "2026-03-06 23:00",
+            "2026-03-07 01:00",
+            "2026-03-07 22:00",
+            "2026-03-09 08:00",
+            "2026-03-10 02:00",
+            "2026-03-10 11:00",
+        ];
+        assert_eq!(
+            expired(retention, &times, NOW),
+            ["2026-03-06 23:00", "2026-03-07 01:00", "2026-03-10 02:00"]
+        );
+    }
+
+    #[test]
+    fn weekly_uses_iso_weeks_across_the_new_year() {
+        let retention = Retention {
+            keep_weekly: Some(2),
+            ..Retention::default()
+        };
+        // December 29-31 2025 belong to ISO week 1 of 2026.
+        let times = [
+            "2025-12-21 12:00",
+            "2025-12-28 12:00",
+            "2025-12-29 12:00",
+            "2026-01-02 12:00",
+        ];
+        assert_eq!(
+            expired(retention, &times, "2026-01-03 00:00"),
+            ["2025-12-21 12:00", "2025-12-29 12:00"]
+        );
+    }
+
+    #[test]
+    fn monthly_keeps_the_newest_of_each_month() {
+        let retention = Retention {
+            keep_monthly: Some(2),
+            ..Retention::default()
+        };
+        let times = [
+            "2025-12-31 23:00",
+            "2026-01-01 00:00",
+            "2026-01-31 23:59",
+            "2026-02-01 00:00",
+            "2026-02-15 00:00",
+        ];
+        assert_eq!(
+            expired(retention, &times, NOW),
+            ["2025-12-31 23:00", "2026-01-01 00:00", "2026-02-01 00:00"]
+        );
+    }
+
+    #[test]
+    fn an_archive_selected_by_any_rule_is_kept() {
+        let retention = Retention {
+            keep_last: Some(2),
+            keep_daily: Some(2),
+            keep_monthly: Some(2),
+            ..Retention::default()
+        };
+        let times = [
+            "2026-02-10 00:00",
+            "2026-02-20 00:00",
+            "2026-03-09 00:00",
+            "2026-03-09 06:00",
+            "2026-03-10 06:00",
+            "2026-03-10 09:00",
+            "2026-03-10 11:00",
+        ];
+        // last: 03-10 11:00, 09:00. daily: 03-10 11:00, 03-09 06:00.
+        // monthly: 03-10 11:00, 02-20.
+        assert_eq!(
+            expired(retention, &times, NOW),
+            ["2026-02-10 00:00", "2026-03-09 00:00", "2026-03-10 06:00"]
+        );
+    }
+
+    #[test]
+    fn max_age_overrides_keep_rules() {
+        let retention = Retention {
+            keep_monthly: Some(12),
+            max_age: Some(30),
+            ..Retention::default()
+        };
+        let times = ["2025-12-15 00:00", "2026-01-20 00:00", "2026-03-01 00:00"];
+        assert_eq!(
+            expired(retention, &times, NOW),
+            ["2025-12-15 00:00", "2026-01-20 00:00"]
+        );
+    }
+
+    #[test]
+    fn max_age_alone_keeps_every_younger_archive() {
+        let retention = Retention {
+            max_age: Some(7),
+            ..Retention::default()
+        };
+        let times = [
+            "2026-03-03 11:59",
+            "2026-03-03 12:00",
+            "2026-03-09 00:00",
+            "2026-03-10 11:00",
+        ];
+        // Exactly seven days old is not yet too old.
+        assert_eq!(expired(retention, &times, NOW), ["2026-03-03 11:59"]);
+    }
+
+    #[test]
+    fn describe_lists_the_rules() {
+        let retention = Retention {
+            keep_last: Some(7),
+            keep_weekly: Some(4),
+            max_age: Some(90),
+            ..Retention::default()
+        };
+        assert_eq!(retention.describe(), "last 7, weekly 4, max age 90d");
+        assert!(!retention.is_empty());
+        assert!(Retention::default().is_empty());
+    }
+}
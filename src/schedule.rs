@@ -0,0 +1,534 @@
+use anyhow::{bail, Result};
+use chrono::{DateTime, Datelike, Duration, Local, LocalResult, NaiveDate, TimeZone};
+
+const WEEKDAYS: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// How far ahead `next_runs` looks before giving up on a schedule that
+/// can never fire, such as `0 0 31 2 *`.
+const SEARCH_DAYS: i64 = 366 * 8;
+
+/// Expands `@daily` and friends to their five-field form.
+fn expand_macro(expr: &str) -> Result<Option<&'static str>> {
+    let expanded = match expr {
+        "@yearly" | "@annually" => "0 0 1 1 *",
+        "@monthly" => "0 0 1 * *",
+        "@weekly" => "0 0 * * 0",
+        "@daily" | "@midnight" => "0 0 * * *",
+        "@hourly" => "0 * * * *",
+        "@reboot" => bail!("'@reboot' is not supported, rcloneup needs a recurring schedule"),
+        _ if expr.starts_with('@') => bail!(
+            "Unknown cron macro '{}', expected one of @yearly, @annually, @monthly, @weekly, @daily, @midnight or @hourly",
+            expr
+        ),
+        _ => return Ok(None),
+    };
+    Ok(Some(expanded))
+}
+
+/// Describes one of the five cron fields.
+struct FieldSpec {
+    name: &'static str,
+    min: u32,
+    max: u32,
+    names: &'static [&'static str],
+    /// Value of `names[0]`.
+    names_start: u32,
+}
+
+const MINUTE: FieldSpec = FieldSpec {
+    name: "minute",
+    min: 0,
+    max: 59,
+    names: &[],
+    names_start: 0,
+};
+const HOUR: FieldSpec = FieldSpec {
+    name: "hour",
+    min: 0,
+    max: 23,
+    names: &[],
+    names_start: 0,
+};
+const DAY_OF_MONTH: FieldSpec = FieldSpec {
+    name: "day-of-month",
+    min: 1,
+    max: 31,
+    names: &[],
+    names_start: 0,
+};
+const MONTH: FieldSpec = FieldSpec {
+    name: "month",
+    min: 1,
+    max: 12,
+    names: &MONTHS,
+    names_start: 1,
+};
+const DAY_OF_WEEK: FieldSpec = FieldSpec {
+    name: "day-of-week",
+    min: 0,
+    max: 7,
+    names: &WEEKDAYS,
+    names_start: 0,
+};
+
+impl FieldSpec {
+    fn value(&self, field: &str, text: &str) -> Result<u32> {
+        if let Some(i) = self.names.iter().position(|n| n.eq_ignore_ascii_case(text)) {
+            return Ok(self.names_start + i as u32);
+        }
+        let Ok(value) = text.parse::<u32>() else {
+            if self.names.is_empty() {
+                bail!(
+                    "Invalid cron {} field '{}': '{}' is not a number",
+                    self.name,
+                    field,
+                    text
+                );
+            }
+            bail!(
+                "Invalid cron {} field '{}': '{}' is not a number or one of {}",
+                self.name,
+                field,
+                text,
+                self.names.join(", ")
+            );
+        };
+        if value < self.min || value > self.max {
+            bail!(
+                "Invalid cron {} field '{}': {} is out of range {}-{}",
+                self.name,
+                field,
+                value,
+                self.min,
+                self.max
+            );
+        }
+        Ok(value)
+    }
+
+    fn parse(&self, text: &str) -> Result<Field> {
+        let mut values = Vec::new();
+        for part in text.split(',') {
+            if part.is_empty() {
+                bail!(
+                    "Invalid cron {} field '{}': empty list item",
+                    self.name,
+                    text
+                );
+            }
+            let (range, step) = match part.split_once('/') {
+                Some((range, step)) => {
+                    let Ok(step) = step.parse::<u32>() else {
+                        bail!(
+                            "Invalid cron {} field '{}': step '{}' is not a number",
+                            self.name,
+                            text,
+                            step
+                        );
+                    };
+                    if step == 0 {
+                        bail!(
+                            "Invalid cron {} field '{}': step must be greater than 0",
+                            self.name,
+                            text
+                        );
+                    }
+                    (range, Some(step))
+                }
+                None => (part, None),
+            };
+            let (start, end) = if range == "*" {
+                (self.min, self.max)
+            } else if let Some((start, end)) = range.split_once('-') {
+                let (start, end) = (self.value(text, start)?, self.value(text, end)?);
+                if start > end {
+                    bail!(
+                        "Invalid cron {} field '{}': range {}-{} runs backwards",
+                        self.name,
+                        text,
+                        start,
+                        end
+                    );
+                }
+                (start, end)
+            } else {
+                let value = self.value(text, range)?;
+                // `5/15` means "from 5 to the end, every 15".
+                (value, if step.is_some() { self.max } else { value })
+            };
+            values.extend((start..=end).step_by(step.unwrap_or(1) as usize));
+        }
+        values.sort_unstable();
+        values.dedup();
+        Ok(Field {
+            star: text.starts_with('*'),
+            values,
+        })
+    }
+}
+
+/// One field of a cron expression, expanded to the values it matches.
+#[derive(Debug, Clone)]
+struct Field {
+    /// True when the field starts with `*`, which is what decides whether
+    /// cron combines the two day fields with "or" or "and".
+    star: bool,
+    values: Vec<u32>,
+}
+
+impl Field {
+    fn contains(&self, value: u32) -> bool {
+        self.values.binary_search(&value).is_ok()
+    }
+
+    fn join(&self, spec: &FieldSpec, render: impl Fn(u32) -> String) -> String {
+        if self.values.len() as u32 == spec.max - spec.min + 1 {
+            return "*".to_string();
+        }
+        self.values
+            .iter()
+            .map(|v| render(*v))
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+}
+
+/// A parsed cron expression.
+#[derive(Debug, Clone)]
+pub struct CronSchedule {
+    minute: Field,
+    hour: Field,
+    day_of_month: Field,
+    month: Field,
+    day_of_week: Field,
+}
+
+impl CronSchedule {
+    /// Parses a five-field expression or one of the `@` macros. Fields
+    /// accept `*`, numbers, month and weekday names, ranges, steps and lists.
+    pub fn parse(expr: &str) -> Result<Self> {
+        let expr = expr.trim();
+        let expr = expand_macro(expr)?.unwrap_or(expr);
+        let fields: Vec<&str> = expr.split_whitespace().collect();
+        if fields.len() != 5 {
+            bail!(
+                "Cron schedule must have exactly 5 fields (minute hour day-of-month month day-of-week), got {} in '{}'",
+                fields.len(),
+                expr
+            );
+        }
+        let mut day_of_week = DAY_OF_WEEK.parse(fields[4])?;
+        // Both 0 and 7 mean Sunday.
+        if day_of_week.values.last() == Some(&7) {
+            day_of_week.values.pop();
+            if !day_of_week.contains(0) {
+                day_of_week.values.insert(0, 0);
+            }
+        }
+        Ok(Self {
+            minute: MINUTE.parse(fields[0])?,
+            hour: HOUR.parse(fields[1])?,
+            day_of_month: DAY_OF_MONTH.parse(fields[2])?,
+            month: MONTH.parse(fields[3])?,
+            day_of_week,
+        })
+    }
+
+    fn matches_day(&self, date: NaiveDate) -> bool {
+        if !self.month.contains(date.month()) {
+            return false;
+        }
+        let dom = self.day_of_month.contains(date.day());
+        let dow = self
+            .day_of_week
+            .contains(date.weekday().num_days_from_sunday());
+        // cron fires when either day field matches unless one of them is `*`.
+        if self.day_of_month.star || self.day_of_week.star {
+            dom && dow
+        } else {
+            dom || dow
+        }
+    }
+
+    /// Returns up to `count` fire times after `after`, in `after`'s time
+    /// zone, which is `Local` outside of tests.
+    pub fn next_runs<Tz: TimeZone>(&self, after: DateTime<Tz>, count: usize) -> Vec<DateTime<Tz>> {
+        let mut runs = Vec::new();
+        let start = after.date_naive();
+        for offset in 0..SEARCH_DAYS {
+            let date = start + Duration::days(offset);
+            if !self.matches_day(date) {
+                continue;
+            }
+            for hour in &self.hour.values {
+                for minute in &self.minute.values {
+                    let Some(naive) = date.and_hms_opt(*hour, *minute, 0) else {
+                        continue;
+                    };
+                    // Times skipped by a DST change never fire; repeated ones
+                    // fire once, the first time round. `earliest` is not
+                    // used since chrono's `Local` may list the later first.
+                    let time = match after.timezone().from_local_datetime(&naive) {
+                        LocalResult::Single(time) => time,
+                        LocalResult::Ambiguous(a, b) => a.min(b),
+                        LocalResult::None => continue,
+                    };
+                    if time > after {
+                        runs.push(time);
+                        if runs.len() == count {
+                            return runs;
+                        }
+                    }
+                }
+            }
+        }
+        runs
+    }
+
+    /// Translates the schedule into a systemd `OnCalendar=` expression.
+    pub fn to_on_calendar(&self) -> Result<String> {
+        // cron fires when either day field matches, systemd only when both do.
+        if !self.day_of_month.star && !self.day_of_week.star {
+            bail!("Cron schedules restricting both day-of-month and day-of-week cannot be expressed as a systemd timer");
+        }
+        let number = |v: u32| v.to_string();
+        let mut calendar = String::new();
+        let weekdays = FieldSpec {
+            max: 6,
+            ..DAY_OF_WEEK
+        };
+        if self.day_of_week.join(&weekdays, number) != "*" {
+            calendar.push_str(
+                &self
+                    .day_of_week
+                    .join(&weekdays, |v| WEEKDAYS[v as usize].to_string()),
+            );
+            calendar.push(' ');
+        }
+        calendar.push_str(&format!(
+            "*-{}-{} {}:{}:00",
+            self.month.join(&MONTH, number),
+            self.day_of_month.join(&DAY_OF_MONTH, number),
+            self.hour.join(&HOUR, number),
+            self.minute.join(&MINUTE, number)
+        ));
+        Ok(calendar)
+    }
+}
+
+/// Formats a fire time the way `--show-next` prints it.
+pub fn format_run(time: &DateTime<Local>) -> String {
+    time.format("%a %Y-%m-%d %H:%M %:z").to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{FixedOffset, NaiveDateTime};
+
+    /// Central European time, spelled out so tests need neither a tz
+    /// database nor `TZ`. In 2026 clocks skip 02:00-03:00 on March 29 and
+    /// repeat 02:00-03:00 on October 25.
+    #[derive(Debug, Clone, Copy)]
+    struct Cet;
+
+    /// 01:00 UTC on the last Sunday of `month`, when the clocks change.
+    fn change(year: i32, month: u32) -> NaiveDateTime {
+        let last = NaiveDate::from_ymd_opt(year, month, 31).unwrap();
+        let sunday = last - Duration::days(last.weekday().num_days_from_sunday().into());
+        sunday.and_hms_opt(1, 0, 0).unwrap()
+    }
+
+    impl TimeZone for Cet {
+        type Offset = FixedOffset;
+
+        fn from_offset(_: &FixedOffset) -> Self {
+            Cet
+        }
+
+        fn offset_from_utc_datetime(&self, utc: &NaiveDateTime) -> FixedOffset {
+            let summer = *utc >= change(utc.year(), 3) && *utc < change(utc.year(), 10);
+            FixedOffset::east_opt(if summer { 7200 } else { 3600 }).unwrap()
+        }
+
+        fn offset_from_utc_date(&self, utc: &NaiveDate) -> FixedOffset {
+            self.offset_from_utc_datetime(&utc.and_hms_opt(0, 0, 0).unwrap())
+        }
+
+        /// Winter time first, so that like `Local` the later instant of a
+        /// repeated time comes first.
+        fn offset_from_local_datetime(&self, local: &NaiveDateTime) -> LocalResult<FixedOffset> {
+            let valid: Vec<FixedOffset> = [3600, 7200]
+                .into_iter()
+                .map(|secs| FixedOffset::east_opt(secs).unwrap())
+                .filter(|offset| {
+                    let utc = *local - Duration::seconds(offset.local_minus_utc().into());
+                    self.offset_from_utc_datetime(&utc) == *offset
+                })
+                .collect();
+            match valid[..] {
+                [] => LocalResult::None,
+                [offset] => LocalResult::Single(offset),
+                [winter, summer] => LocalResult::Ambiguous(winter, summer),
+                _ => unreachable!(),
+            }
+        }
+
+        fn offset_from_local_date(&self, local: &NaiveDate) -> LocalResult<FixedOffset> {
+            self.offset_from_local_datetime(&local.and_hms_opt(0, 0, 0).unwrap())
+        }
+    }
+
+    fn cet(text: &str) -> DateTime<Cet> {
+        let naive = NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M").unwrap();
+        Cet.from_local_datetime(&naive).single().unwrap()
+    }
+
+    fn runs(expr: &str, after: &str, count: usize) -> Vec<String> {
+        CronSchedule::parse(expr)
+            .unwrap()
+            .next_runs(cet(after), count)
+            .iter()
+            .map(|t| t.format("%a %m-%d %H:%M %:z").to_string())
+            .collect()
+    }
+
+    #[test]
+    fn restricted_day_fields_match_either() {
+        // The 13th, or any Friday.
+        assert_eq!(
+            runs("0 0 13 * 5", "2026-03-01 00:00", 8),
+            [
+                "Fri 03-06 00:00 +01:00",
+                "Fri 03-13 00:00 +01:00",
+                "Fri 03-20 00:00 +01:00",
+                "Fri 03-27 00:00 +01:00",
+                "Fri 04-03 00:00 +02:00",
+                "Fri 04-10 00:00 +02:00",
+                "Mon 04-13 00:00 +02:00",
+                "Fri 04-17 00:00 +02:00",
+            ]
+        );
+    }
+
+    #[test]
+    fn starred_day_field_makes_both_match() {
+        // `*/2` starts with `*`, so only Fridays on odd days fire.
+        assert_eq!(
+            runs("0 0 */2 * fri", "2026-03-01 00:00", 4),
+            [
+                "Fri 03-13 00:00 +01:00",
+                "Fri 03-27 00:00 +01:00",
+                "Fri 04-03 00:00 +02:00",
+                "Fri 04-17 00:00 +02:00",
+            ]
+        );
+        assert_eq!(
+            runs("0 0 13 * *", "2026-03-01 00:00", 2),
+            ["Fri 03-13 00:00 +01:00", "Mon 04-13 00:00 +02:00"]
+        );
+    }
+
+    #[test]
+    fn sunday_is_zero_and_seven() {
+        let sundays = runs("0 9 * * 0", "2026-03-01 10:00", 3);
+        assert_eq!(
+            sundays,
+            [
+                "Sun 03-08 09:00 +01:00",
+                "Sun 03-15 09:00 +01:00",
+                "Sun 03-22 09:00 +01:00",
+            ]
+        );
+        assert_eq!(runs("0 9 * * 7", "2026-03-01 10:00", 3), sundays);
+        assert_eq!(runs("0 9 * * Sun", "2026-03-01 10:00", 3), sundays);
+        assert_eq!(
+            runs("0 9 * * 6-7", "2026-03-01 10:00", 3),
+            [
+                "Sat 03-07 09:00 +01:00",
+                "Sun 03-08 09:00 +01:00",
+                "Sat 03-14 09:00 +01:00",
+            ]
+        );
+        assert_eq!(
+            CronSchedule::parse("0 9 * * 0,7")
+                .unwrap()
+                .day_of_week
+                .values,
+            [0]
+        );
+    }
+
+    #[test]
+    fn skipped_times_never_fire() {
+        assert_eq!(
+            runs("30 2 * * *", "2026-03-28 00:00", 2),
+            ["Sat 03-28 02:30 +01:00", "Mon 03-30 02:30 +02:00"]
+        );
+        // Other times that day still fire, at the new offset.
+        assert_eq!(
+            runs("0 3 * * *", "2026-03-28 12:00", 1),
+            ["Sun 03-29 03:00 +02:00"]
+        );
+    }
+
+    #[test]
+    fn repeated_times_fire_once() {
+        assert_eq!(
+            runs("30 2 * * *", "2026-10-24 12:00", 2),
+            ["Sun 10-25 02:30 +02:00", "Mon 10-26 02:30 +01:00"]
+        );
+    }
+
+    #[test]
+    fn impossible_dates_never_fire() {
+        assert!(runs("0 0 31 2 *", "2026-01-01 00:00", 1).is_empty());
+        assert_eq!(
+            runs("0 0 29 2 *", "2026-01-01 00:00", 1),
+            ["Tue 02-29 00:00 +01:00"]
+        );
+    }
+
+    #[test]
+    fn macros_expand() {
+        assert_eq!(
+            runs("@weekly", "2026-03-01 10:00", 1),
+            ["Sun 03-08 00:00 +01:00"]
+        );
+        assert!(CronSchedule::parse("@reboot").is_err());
+        assert!(CronSchedule::parse("@fortnightly").is_err());
+    }
+
+    #[test]
+    fn rejects_bad_fields() {
+        for expr in [
+            "0 0 * *",
+            "60 * * * *",
+            "* 24 * * *",
+            "* * 0 * *",
+            "* * * 13 *",
+            "* * * * 8",
+            "5-1 * * * *",
+            "*/0 * * * *",
+            "1,,2 * * * *",
+            "* * * Foo *",
+        ] {
+            assert!(CronSchedule::parse(expr).is_err(), "{}", expr);
+        }
+    }
+
+    #[test]
+    fn on_calendar_translation() {
+        let calendar = |expr| CronSchedule::parse(expr).unwrap().to_on_calendar();
+        assert_eq!(calendar("0 3 * * *").unwrap(), "*-*-* 3:0:00");
+        assert!(calendar("15,45 */6 1 jan-mar 1-5").is_err());
+        assert_eq!(
+            calendar("15,45 */6 * jan-mar 1-5").unwrap(),
+            "Mon,Tue,Wed,Thu,Fri *-1,2,3-* 0,6,12,18:15,45:00"
+        );
+        assert_eq!(calendar("0 0 * * 0,7").unwrap(), "Sun *-*-* 0:0:00");
+    }
+}
@@ -0,0 +1,87 @@
+use serde::Deserialize;
+use std::{
+    convert::Infallible,
+    fmt,
+    str::FromStr,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+const MASK: &str = "********";
+
+/// Set once at startup from `--show-secrets`.
+static SHOW_SECRETS: AtomicBool = AtomicBool::new(false);
+
+pub fn set_show_secrets(show: bool) {
+    SHOW_SECRETS.store(show, Ordering::Relaxed);
+}
+
+fn show_secrets() -> bool {
+    SHOW_SECRETS.load(Ordering::Relaxed)
+}
+
+/// A credential whose `Debug` and `Display` output is masked unless
+/// `--show-secrets` was given. Use `expose` where the real value is needed.
+#[derive(Clone, PartialEq, Eq, Deserialize)]
+#[serde(transparent)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn new(value: impl Into<String>) -> Self {
+        Self(value.into())
+    }
+
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl FromStr for Secret {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self::new(s))
+    }
+}
+
+impl fmt::Display for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if show_secrets() {
+            f.write_str(&self.0)
+        } else {
+            f.write_str(MASK)
+        }
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Secret({})", self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // `--show-secrets` is process-wide, so these only check the default.
+
+    #[test]
+    fn secrets_are_masked_unless_exposed() {
+        let secret: Secret = "hunter2".parse().unwrap();
+        assert_eq!(secret.to_string(), MASK);
+        assert_eq!(format!("{:?}", secret), "Secret(********)");
+        assert_eq!(format!("{:?}", Some(&secret)), "Some(Secret(********))");
+        assert_eq!(secret.expose(), "hunter2");
+    }
+
+    #[test]
+    fn deserialized_secrets_are_masked() {
+        #[derive(Debug, Deserialize)]
+        struct Remote {
+            secret_key: Secret,
+        }
+        let remote: Remote = toml::from_str("secret_key = \"hunter2\"").unwrap();
+        assert_eq!(remote.secret_key.expose(), "hunter2");
+        assert!(!format!("{:?}", remote).contains("hunter2"));
+    }
+}
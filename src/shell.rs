@@ -0,0 +1,104 @@
+use crate::secret::Secret;
+use anyhow::{bail, Result};
+
+/// Quotes `value` for a POSIX shell: everything goes inside single quotes,
+/// and each embedded `'` becomes `'\''`.
+pub fn quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+/// Quotes `value` for the command part of a crontab line, where cron
+/// itself turns an unescaped `%` into a newline before the shell sees it.
+pub fn quote_crontab(value: &str) -> String {
+    quote(value).replace('%', r"\%")
+}
+
+/// Rejects values that no amount of quoting makes safe to embed in the
+/// generated script, crontab, systemd units or ini-style config files.
+pub fn reject_control_chars(what: &str, value: &str) -> Result<()> {
+    if let Some(problem) = control_chars(value) {
+        bail!("{} must not contain {}: {:?}", what, problem, value);
+    }
+    Ok(())
+}
+
+/// Like `reject_control_chars`, but leaves the value out of the error so
+/// that credentials never reach the terminal or CI logs.
+pub fn reject_secret_control_chars(what: &str, value: &Secret) -> Result<()> {
+    if let Some(problem) = control_chars(value.expose()) {
+        bail!("{} must not contain {}", what, problem);
+    }
+    Ok(())
+}
+
+fn control_chars(value: &str) -> Option<&'static str> {
+    if value.contains('\0') {
+        Some("NUL bytes")
+    } else if value.contains('\n') || value.contains('\r') {
+        Some("line breaks")
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::HOSTILE_PATHS as HOSTILE;
+    use std::process::Command;
+
+    /// Runs `words` through sh and returns the arguments it saw.
+    fn sh_args(words: &str) -> Vec<String> {
+        let output = Command::new("sh")
+            .arg("-c")
+            .arg(format!("printf '%s\\0' {}", words))
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        String::from_utf8(output.stdout)
+            .unwrap()
+            .split_terminator('\0')
+            .map(String::from)
+            .collect()
+    }
+
+    #[test]
+    fn quote_survives_the_shell() {
+        for value in HOSTILE {
+            assert_eq!(sh_args(&quote(value)), [value], "{}", quote(value));
+        }
+    }
+
+    #[test]
+    fn quote_keeps_words_apart() {
+        let words = HOSTILE.map(quote).join(" ");
+        assert_eq!(sh_args(&words), HOSTILE);
+    }
+
+    #[test]
+    fn quote_crontab_escapes_percent() {
+        assert_eq!(quote_crontab("/data/100% done"), r"'/data/100\% done'");
+        // cron drops the backslash before `%`, which leaves the shell word.
+        let after_cron = quote_crontab("/data/a%b%%c").replace(r"\%", "%");
+        assert_eq!(sh_args(&after_cron), ["/data/a%b%%c"]);
+    }
+
+    #[test]
+    fn rejects_line_breaks_and_nul() {
+        for value in ["a\nb", "a\rb", "a\0b"] {
+            assert!(reject_control_chars("Source", value).is_err());
+        }
+        for value in HOSTILE {
+            assert!(reject_control_chars("Source", value).is_ok());
+        }
+    }
+
+    #[test]
+    fn secret_errors_leave_the_value_out() {
+        let secret = Secret::new("SUPERSECRET\nx");
+        let error = reject_secret_control_chars("Secret key", &secret).unwrap_err();
+        assert_eq!(error.to_string(), "Secret key must not contain line breaks");
+        let error = reject_control_chars("Source", "a\0b").unwrap_err();
+        assert!(error.to_string().contains("NUL"));
+    }
+}
@@ -0,0 +1,155 @@
+use crate::{backup::RunStats, paths::JobPaths};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt,
+    fs::{self, OpenOptions},
+    io::Write,
+    os::unix::fs::OpenOptionsExt,
+};
+
+/// How many runs each job's history keeps; older ones are dropped.
+pub const HISTORY_LIMIT: usize = 100;
+
+/// How a run of `rcloneup run` ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Outcome {
+    Success,
+    /// rclone failed, or the run could not start it.
+    Failed,
+    /// The previous run still held the lock.
+    Skipped,
+    /// A pre-flight guard stopped the run.
+    Refused,
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Outcome::Success => write!(f, "success"),
+            Outcome::Failed => write!(f, "failed"),
+            Outcome::Skipped => write!(f, "skipped"),
+            Outcome::Refused => write!(f, "refused"),
+        }
+    }
+}
+
+/// One line of a job's run history.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunRecord {
+    pub job: String,
+    pub started: DateTime<Utc>,
+    pub finished: DateTime<Utc>,
+    pub outcome: Outcome,
+    /// rclone's exit code, if it ran and was not killed by a signal.
+    pub exit_code: Option<i32>,
+    #[serde(flatten)]
+    pub stats: RunStats,
+    /// The first few errors rclone logged, even when it recovered from them.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rclone_errors: Vec<String>,
+    pub error: Option<String>,
+    /// Why pruning old archives failed after an otherwise good backup.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prune_error: Option<String>,
+}
+
+/// Appends `record` to the job's history, keeping the newest
+/// `HISTORY_LIMIT` runs. Writers take an flock on a separate lock file
+/// and replace the history atomically, so readers never see half of it.
+pub fn append(job_paths: &JobPaths, record: &RunRecord) -> Result<()> {
+    let lock = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .mode(0o600)
+        .open(&job_paths.history_lock)
+        .with_context(|| format!("Failed to open {}", job_paths.history_lock.display()))?;
+    lock.lock()
+        .with_context(|| format!("Failed to lock {}", job_paths.history_lock.display()))?;
+
+    let mut runs = load(job_paths)?;
+    runs.push(record.clone());
+    let excess = runs.len().saturating_sub(HISTORY_LIMIT);
+
+    let tmp = job_paths.history.with_extension("jsonl.tmp");
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(&tmp)
+        .with_context(|| format!("Failed to write {}", tmp.display()))?;
+    for run in &runs[excess..] {
+        serde_json::to_writer(&mut file, run)?;
+        file.write_all(b"\n")?;
+    }
+    file.sync_all()?;
+    fs::rename(&tmp, &job_paths.history)
+        .with_context(|| format!("Failed to replace {}", job_paths.history.display()))?;
+    Ok(())
+}
+
+/// The job's recorded runs, oldest first. Lines that cannot be parsed
+/// are skipped rather than hiding the rest of the history.
+pub fn load(job_paths: &JobPaths) -> Result<Vec<RunRecord>> {
+    if !job_paths.history.exists() {
+        return Ok(Vec::new());
+    }
+    let text = fs::read_to_string(&job_paths.history)
+        .with_context(|| format!("Failed to read {}", job_paths.history.display()))?;
+    Ok(text
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{notify, testing::TempDir};
+
+    fn run(n: u32) -> RunRecord {
+        let mut record = notify::sample_record("default", n.is_multiple_of(2));
+        record.exit_code = Some(n as i32);
+        record
+    }
+
+    #[test]
+    fn append_keeps_the_newest_runs() {
+        let dir = TempDir::new();
+        let job_paths = dir.paths().job("default");
+        fs::create_dir_all(&job_paths.dir).unwrap();
+        assert!(load(&job_paths).unwrap().is_empty());
+
+        let total = HISTORY_LIMIT as u32 + 5;
+        for n in 0..total {
+            append(&job_paths, &run(n)).unwrap();
+        }
+        let runs = load(&job_paths).unwrap();
+        assert_eq!(runs.len(), HISTORY_LIMIT);
+        let codes: Vec<_> = runs.iter().map(|r| r.exit_code.unwrap()).collect();
+        assert_eq!(codes, (5..total as i32).collect::<Vec<_>>());
+        assert_eq!(runs[0].outcome, Outcome::Failed);
+        assert_eq!(runs[1].outcome, Outcome::Success);
+        assert!(!job_paths.history.with_extension("jsonl.tmp").exists());
+    }
+
+    #[test]
+    fn load_skips_lines_it_cannot_parse() {
+        let dir = TempDir::new();
+        let job_paths = dir.paths().job("default");
+        fs::create_dir_all(&job_paths.dir).unwrap();
+        append(&job_paths, &run(1)).unwrap();
+        let mut text = fs::read_to_string(&job_paths.history).unwrap();
+        text.push_str("{\"job\": \"truncated\n");
+        fs::write(&job_paths.history, text).unwrap();
+        append(&job_paths, &run(2)).unwrap();
+
+        let runs = load(&job_paths).unwrap();
+        let codes: Vec<_> = runs.iter().map(|r| r.exit_code).collect();
+        assert_eq!(codes, [Some(1), Some(2)]);
+    }
+}
@@ -0,0 +1,238 @@
+use crate::{
+    files::{remove_if_exists, write_if_changed},
+    paths::JobPaths,
+};
+use anyhow::{bail, Context, Result};
+use std::process::Command;
+
+fn systemctl(args: &[&str], verbose: bool) -> Result<()> {
+    if verbose {
+        println!("Running: systemctl --user {}", args.join(" "));
+    }
+    let status = Command::new("systemctl")
+        .arg("--user")
+        .args(args)
+        .status()
+        .context("Failed to run systemctl")?;
+    if !status.success() {
+        bail!(
+            "'systemctl --user {}' failed with {}",
+            args.join(" "),
+            status
+        );
+    }
+    Ok(())
+}
+
+/// Quotes `value` as a single word of a systemd `ExecStart=` line, where
+/// `%` starts a specifier and `$` an environment variable.
+fn quote_exec(value: &str) -> String {
+    let escaped = value
+        .replace('\\', r"\\")
+        .replace('"', r#"\""#)
+        .replace('%', "%%")
+        .replace('$', "$$");
+    format!("\"{}\"", escaped)
+}
+
+fn service_content(job: &str, command: &[String]) -> String {
+    format!(
+        r#"[Unit]
+Description=rcloneup backup job '{job}'
+
+[Service]
+Type=oneshot
+ExecStart={command}
+"#,
+        job = job,
+        command = command
+            .iter()
+            .map(|word| quote_exec(word))
+            .collect::<Vec<_>>()
+            .join(" ")
+    )
+}
+
+fn timer_content(job: &str, on_calendar: &str) -> String {
+    format!(
+        r#"[Unit]
+Description=Run rcloneup backup job '{job}'
+
+[Timer]
+OnCalendar={on_calendar}
+Persistent=true
+
+[Install]
+WantedBy=timers.target
+"#,
+        job = job,
+        on_calendar = on_calendar
+    )
+}
+
+/// Writes the job's `.service` unit running `command` and its `.timer`
+/// unit, and enables the timer.
+pub fn install_timer(
+    job: &str,
+    job_paths: &JobPaths,
+    command: &[String],
+    on_calendar: &str,
+    dry_run: bool,
+    verbose: bool,
+) -> Result<()> {
+    let service = service_content(job, command);
+    let timer = timer_content(job, on_calendar);
+
+    if dry_run {
+        println!(
+            "(dry-run) Would write systemd units {} and {}",
+            job_paths.service_unit.display(),
+            job_paths.timer_unit.display()
+        );
+        if verbose {
+            println!("--- service unit content ---\n{}", service);
+            println!("--- timer unit content ---\n{}", timer);
+        }
+        println!(
+            "(dry-run) Would enable systemd timer {} with OnCalendar={}",
+            job_paths.timer_name(),
+            on_calendar
+        );
+        return Ok(());
+    }
+
+    if let Some(dir) = job_paths.timer_unit.parent() {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create systemd user directory {:?}", dir))?;
+    }
+    let service_changed =
+        write_if_changed(&job_paths.service_unit, service.as_bytes(), 0o644, verbose)?;
+    let timer_changed = write_if_changed(&job_paths.timer_unit, timer.as_bytes(), 0o644, verbose)?;
+    if service_changed || timer_changed {
+        systemctl(&["daemon-reload"], verbose)?;
+    }
+    systemctl(&["enable", "--now", &job_paths.timer_name()], verbose)?;
+
+    if verbose {
+        println!("Systemd timer enabled successfully.");
+    }
+    Ok(())
+}
+
+/// Disables and deletes the job's units, if there are any.
+pub fn remove_timer(job_paths: &JobPaths, dry_run: bool, verbose: bool) -> Result<()> {
+    if !job_paths.service_unit.exists() && !job_paths.timer_unit.exists() {
+        if verbose {
+            println!("No systemd timer found for {}", job_paths.timer_name());
+        }
+        return Ok(());
+    }
+
+    if dry_run {
+        println!(
+            "(dry-run) Would disable systemd timer {}",
+            job_paths.timer_name()
+        );
+    } else if let Err(e) = systemctl(&["disable", "--now", &job_paths.timer_name()], verbose) {
+        // Still remove the unit files; a unit that fails to disable is
+        // usually one systemd never loaded.
+        println!("Warning: {:#}", e);
+    }
+    remove_if_exists(&job_paths.timer_unit, dry_run, verbose)?;
+    remove_if_exists(&job_paths.service_unit, dry_run, verbose)?;
+    if !dry_run {
+        systemctl(&["daemon-reload"], verbose)?;
+    }
+    Ok(())
+}
+
+/// Whether the job's installed units are exactly what `install_timer`
+/// would write for `command` and `on_calendar`.
+pub fn units_match(job: &str, job_paths: &JobPaths, command: &[String], on_calendar: &str) -> bool {
+    let read = |path| std::fs::read_to_string(path).ok();
+    read(&job_paths.service_unit) == Some(service_content(job, command))
+        && read(&job_paths.timer_unit) == Some(timer_content(job, on_calendar))
+}
+
+/// Returns the result of `systemctl --user is-active` for the job's timer.
+pub fn timer_state(job_paths: &JobPaths) -> Option<String> {
+    if !job_paths.timer_unit.exists() {
+        return None;
+    }
+    let output = Command::new("systemctl")
+        .args(["--user", "is-active", &job_paths.timer_name()])
+        .output()
+        .ok()?;
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+    use std::fs;
+
+    #[test]
+    fn quote_exec_escapes_specifiers_and_variables() {
+        assert_eq!(quote_exec("/data/plain"), "\"/data/plain\"");
+        assert_eq!(quote_exec("/data/100%"), "\"/data/100%%\"");
+        assert_eq!(quote_exec("/data/$HOME"), "\"/data/$$HOME\"");
+        assert_eq!(quote_exec("/data/\"a\""), r#""/data/\"a\"""#);
+        assert_eq!(quote_exec(r"/data/a\b"), r#""/data/a\\b""#);
+        // Single quotes and backticks mean nothing to systemd.
+        assert_eq!(quote_exec("/data/it's `id`"), "\"/data/it's `id`\"");
+    }
+
+    #[test]
+    fn service_runs_hostile_source_as_one_word() {
+        let command = [
+            "/usr/bin/rcloneup".to_string(),
+            "run".to_string(),
+            "/data/it's \"a\" $HOME 100%".to_string(),
+        ];
+        let service = service_content("default", &command);
+        assert!(service.contains(
+            "\nExecStart=\"/usr/bin/rcloneup\" \"run\" \"/data/it's \\\"a\\\" $$HOME 100%%\"\n"
+        ));
+    }
+
+    #[test]
+    fn timer_fires_on_calendar_and_catches_up() {
+        assert_eq!(
+            timer_content("photos", "Mon,Tue,Wed,Thu,Fri *-*-* 2:30:00"),
+            "[Unit]\nDescription=Run rcloneup backup job 'photos'\n\n\
+             [Timer]\nOnCalendar=Mon,Tue,Wed,Thu,Fri *-*-* 2:30:00\nPersistent=true\n\n\
+             [Install]\nWantedBy=timers.target\n"
+        );
+    }
+
+    #[test]
+    fn units_match_only_what_install_writes() {
+        let dir = TempDir::new();
+        let job_paths = dir.paths().job("photos");
+        let command = ["/usr/bin/rcloneup".to_string(), "run".to_string()];
+        assert!(!units_match("photos", &job_paths, &command, "hourly"));
+
+        fs::create_dir_all(&dir.paths().systemd_user_dir).unwrap();
+        fs::write(&job_paths.service_unit, service_content("photos", &command)).unwrap();
+        fs::write(&job_paths.timer_unit, timer_content("photos", "hourly")).unwrap();
+        assert!(units_match("photos", &job_paths, &command, "hourly"));
+        assert!(!units_match("photos", &job_paths, &command, "daily"));
+        assert!(!units_match("photos", &job_paths, &command[..1], "hourly"));
+        assert_eq!(job_paths.timer_name(), "rcloneup-photos.timer");
+    }
+
+    #[test]
+    fn dry_run_install_and_remove_touch_nothing() {
+        let dir = TempDir::new();
+        let job_paths = dir.paths().job("photos");
+        let command = ["/usr/bin/rcloneup".to_string()];
+        install_timer("photos", &job_paths, &command, "hourly", true, false).unwrap();
+        assert!(!dir.paths().systemd_user_dir.exists());
+
+        fs::create_dir_all(&dir.paths().systemd_user_dir).unwrap();
+        fs::write(&job_paths.timer_unit, "").unwrap();
+        remove_timer(&job_paths, true, false).unwrap();
+        assert!(job_paths.timer_unit.exists());
+    }
+}
@@ -0,0 +1,112 @@
+//! Helpers shared by the unit tests.
+
+use crate::{
+    cli::Cli,
+    config::{self, Config, JobSetup},
+    job::{Credentials, Job, Mode, Overlap, Runner, Scheduler},
+    log_rotation::{LogPolicy, LogRotation, DEFAULT_LOG_KEEP, DEFAULT_LOG_MAX_SIZE},
+    paths::Paths,
+    retention::Retention,
+};
+use clap::{CommandFactory, FromArgMatches};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// Source paths that break naive quoting or parsing.
+pub const HOSTILE_PATHS: [&str; 8] = [
+    "/data/it's here",
+    "/data/\"quoted\"",
+    "/data/$HOME",
+    "/data/`id`",
+    "/data/100% done",
+    "/data/a'b\"c$d`e%f\\g",
+    "/data/ spaces  and\ttabs ",
+    " /data/=# not a comment ",
+];
+
+/// A directory below the system temp dir, removed again on drop.
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    pub fn new() -> Self {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let path = std::env::temp_dir().join(format!(
+            "rcloneup-test-{}-{}",
+            process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::create_dir_all(&path).unwrap();
+        Self(path)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+
+    /// Every location rcloneup uses, below this directory.
+    pub fn paths(&self) -> Paths {
+        Paths {
+            rclone_config_dir: self.0.join("rclone"),
+            rclone_config_file: self.0.join("rclone/rclone.conf"),
+            jobs_dir: self.0.join("jobs"),
+            systemd_user_dir: self.0.join("systemd"),
+            recovery_dir: self.0.join("recovery"),
+            legacy_backup_script: self.0.join("rclone_backup.sh"),
+        }
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// A job with setup's defaults backing up `source`.
+pub fn job(name: &str, source: &str) -> Job {
+    Job {
+        name: name.to_string(),
+        source: source.to_string(),
+        remote: "minio".to_string(),
+        bucket: "backups".to_string(),
+        cron: "0 * * * *".to_string(),
+        scheduler: Scheduler::Cron,
+        mode: Mode::Sync,
+        retention: Retention::default(),
+        runner: Runner::Binary,
+        on_overlap: Overlap::Skip,
+        lock_timeout: 0,
+        require_mountpoint: false,
+        sentinel: None,
+        max_drop: None,
+        max_delete: None,
+        logs: LogPolicy {
+            rotation: LogRotation::Rotate,
+            max_size: DEFAULT_LOG_MAX_SIZE,
+            max_age: None,
+            keep: DEFAULT_LOG_KEEP,
+        },
+        notify: None,
+        credentials: Credentials::Plain,
+        config_password: None,
+        crypt_remote: None,
+        config_fingerprint: None,
+    }
+}
+
+/// Resolves `rcloneup setup <args>` against the rcloneup.toml `config`.
+pub fn setup_jobs(args: &[&str], config: &str) -> anyhow::Result<Vec<JobSetup>> {
+    let matches = Cli::command().try_get_matches_from(["rcloneup", "setup"].iter().chain(args))?;
+    let cli = Cli::from_arg_matches(&matches)?;
+    let crate::cli::Command::Setup(setup) = &cli.command else {
+        unreachable!("parsed a setup command");
+    };
+    let config = Config {
+        file: toml::from_str(config)?,
+    };
+    config::resolve_setup(setup, matches.subcommand_matches("setup").unwrap(), &config)
+}